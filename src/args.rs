@@ -6,6 +6,12 @@ use clap::{
 #[derive(Parser, Debug, Clone)]
 #[command(version, about, long_about = None,styles=get_styles())]
 pub struct Cli {
+	/// Override a config setting for this run, e.g. `-c parallel=8`
+	#[arg(short = 'c', long = "config", global = true, value_name = "KEY=VALUE")]
+	pub config: Vec<String>,
+	/// Don't show download progress
+	#[arg(short = 'q', long, global = true)]
+	pub quiet: bool,
 	#[command(subcommand)]
 	pub sub: SubCmd,
 }
@@ -18,6 +24,16 @@ pub enum SubCmd {
 	Instance(InstanceCmd),
 	/// Modloader command
 	Modloader(LoaderCmd),
+	/// Java installations
+	Java(JavaCmd),
+	/// Show or change settings in vanta.toml
+	Config(ConfigCmd),
+	/// Remove assets and libraries no instance uses any more
+	Gc {
+		/// Only list what would be removed
+		#[arg(long)]
+		dry_run: bool,
+	},
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -43,16 +59,91 @@ pub struct InstanceCmd {
 pub enum InstanceSub {
 	Run {
 		id: String,
-		uid: String,
+		/// Account to play as, instead of the configured `default_account`
+		uid: Option<String>,
 	},
 	New {
 		id: String,
 		version: String,
-		#[arg(long, default_value_t = 4)]
-		parallel: usize,
+		/// Downloads at once, instead of the configured `parallel`
+		#[arg(long)]
+		parallel: Option<usize>,
+		/// Attempts per file before giving up on it, instead of the configured `retries`
+		#[arg(long)]
+		retries: Option<usize>,
+	},
+	/// List instances
+	List {
+		/// Only show instances in this group
+		#[arg(long)]
+		group: Option<String>,
+		/// Only show instances with this loader (vanilla, fabric, ...)
+		#[arg(long)]
+		loader: Option<String>,
+		/// Only show instances of this Minecraft version
+		#[arg(long)]
+		version: Option<String>,
+		/// Print JSON instead of a table
+		#[arg(long)]
+		json: bool,
 	},
-	List,
 	VersionList,
+	/// Delete an instance including its worlds
+	Delete {
+		id: String,
+		/// Don't ask for confirmation
+		#[arg(long, short)]
+		yes: bool,
+	},
+	/// Give an instance a new id
+	Rename {
+		id: String,
+		new_id: String,
+	},
+	/// Copy an instance, sharing its game files with the original
+	Clone {
+		id: String,
+		new_id: String,
+	},
+	/// Show or change an instance's memory, JVM arguments and environment
+	Config {
+		id: String,
+		/// Initial heap size like 2G, or `none`
+		#[arg(long)]
+		min_mem: Option<String>,
+		/// Maximum heap size like 8G, or `none`
+		#[arg(long)]
+		max_mem: Option<String>,
+		/// Append a JVM argument; repeat for more
+		#[arg(long, allow_hyphen_values = true)]
+		jvm_arg: Vec<String>,
+		/// Remove the instance's JVM arguments before appending new ones
+		#[arg(long)]
+		clear_jvm_args: bool,
+		/// GC flag set: aikar, g1, zgc (Java 15+), shenandoah (Java 12+) or `none`
+		#[arg(long)]
+		gc_preset: Option<String>,
+		/// Set an environment variable of the game, as KEY=VALUE
+		#[arg(long)]
+		env: Vec<String>,
+		/// Remove an environment variable set with --env
+		#[arg(long)]
+		unset_env: Vec<String>,
+	},
+	/// Check an instance's files for missing or corrupt entries
+	#[command(alias = "repair")]
+	Verify {
+		id: String,
+		/// Re-download missing or corrupt files
+		#[arg(long)]
+		fix: bool,
+		/// Downloads at once, instead of the configured `parallel`
+		#[arg(long)]
+		parallel: Option<usize>,
+		/// Attempts per file before giving up on it, instead of the configured `retries`
+		#[arg(long)]
+		retries: Option<usize>,
+	},
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -63,7 +154,66 @@ pub struct LoaderCmd {
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum LoaderSub {
-	Install { loader: String },
+	/// Install a mod loader (fabric, quilt, forge, neoforge) into an instance
+	Install {
+		instance: String,
+		loader: String,
+		/// Loader version to install instead of the newest stable one
+		#[arg(long)]
+		loader_version: Option<String>,
+		/// Downloads at once, instead of the configured `parallel`
+		#[arg(long)]
+		parallel: Option<usize>,
+		/// Attempts per file before giving up on it, instead of the configured `retries`
+		#[arg(long)]
+		retries: Option<usize>,
+	},
+	/// List the loader versions available for an instance
+	Versions { instance: String, loader: String },
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct JavaCmd {
+	#[command(subcommand)]
+	pub cmd: JavaSub,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum JavaSub {
+	/// List the Java installations found on this machine
+	List {
+		/// Print JSON instead of a table
+		#[arg(long)]
+		json: bool,
+		/// Probe every installation again instead of trusting the cache
+		#[arg(long)]
+		refresh: bool,
+	},
+	/// Pin the Java an instance runs with
+	Use {
+		instance: String,
+		/// A java executable or Java home, a release like `17`, a minimum like
+		/// `>=17`, or `auto` for the Mojang runtime its version asks for
+		java: String,
+	},
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ConfigCmd {
+	#[command(subcommand)]
+	pub cmd: ConfigSub,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigSub {
+	/// Print a setting as it applies to this run, or all of them
+	Get { key: Option<String> },
+	/// Change a setting in the config file
+	Set { key: String, value: String },
+	/// Remove a setting from the config file, going back to its default
+	Unset { key: String },
+	/// Open the config file in $EDITOR
+	Edit,
 }
 
 pub fn get_styles() -> clap::builder::Styles {
@@ -1,4 +1,4 @@
-use crate::constants;
+use crate::config;
 use crate::helpers;
 use crate::helpers::upsert_user;
 use std::io::{self, Write};
@@ -108,15 +108,7 @@ impl<'a> Iterator for AuthParamsIter<'a> {
 
 #[derive(Debug, Deserialize)]
 pub struct McAuthResponse {
-	#[allow(dead_code)]
-	pub username: String,
-	#[allow(dead_code)]
-	pub roles: Vec<String>,
 	pub access_token: String,
-	#[allow(dead_code)]
-	pub token_type: String,
-	#[allow(dead_code)]
-	pub expires_in: i64,
 }
 
 #[allow(non_snake_case)]
@@ -135,22 +127,12 @@ pub struct XSTSTokRequest<'a> {
 #[allow(non_snake_case)]
 #[derive(Debug, Deserialize)]
 pub struct XSTSResponse<'a> {
-	#[allow(dead_code)]
-	IssueInstant: &'a str,
-	#[allow(dead_code)]
-	NotAfter: &'a str,
 	pub Token: &'a str,
-	#[allow(dead_code)]
-	DisplayClaims: DisplayClaims,
 }
 
 #[allow(non_snake_case)]
 #[derive(Debug, Deserialize)]
 pub struct XblAuthResponse {
-	#[allow(dead_code)]
-	pub IssueInstant: String,
-	#[allow(dead_code)]
-	pub NotAfter: String,
 	pub Token: String,
 	pub DisplayClaims: DisplayClaims,
 }
@@ -192,11 +174,7 @@ pub struct AuthTokenParameters {
 #[derive(Debug, Deserialize)]
 pub struct TokenResponse {
 	pub access_token: String,
-	#[allow(dead_code)]
-	pub expires_in: Option<u64>,
 	pub refresh_token: Option<String>,
-	#[allow(dead_code)]
-	pub token_type: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -249,8 +227,8 @@ pub fn get_secure_login_data(
 		response_mode: "query".into(),
 		scope: "XboxLive.signin offline_access".into(),
 		state: state.clone(),
-		code_challenge: code_challenge,
-		code_challenge_method: code_challenge_method,
+		code_challenge,
+		code_challenge_method,
 	};
 
 	let url = Url::parse_with_params(
@@ -259,7 +237,7 @@ pub fn get_secure_login_data(
 	)
 	.unwrap();
 
-	return (url, state, code_verifier);
+	(url, state, code_verifier)
 }
 
 pub async fn get_auth_token(
@@ -323,7 +301,7 @@ pub async fn authenticate_with_xbl(
 	let user_hash = resp
 		.DisplayClaims
 		.xui
-		.get(0)
+		.first()
 		.ok_or("Missing xui/uhs claim")?
 		.uhs
 		.clone();
@@ -404,7 +382,7 @@ pub async fn get_minecraft_profile(
 		skins: resp.skins,
 		capes: resp.capes,
 		access_token: token,
-		refresh_token: refresh_token,
+		refresh_token,
 	})
 }
 
@@ -428,16 +406,17 @@ pub async fn complete_login(
 		.await
 		.unwrap();
 
-	return get_minecraft_profile(mc_auth.access_token, refresh_token.unwrap())
+	get_minecraft_profile(mc_auth.access_token, refresh_token.unwrap())
 		.await
-		.expect("Failed to get the profile. Did you log in using the correct account?");
+		.expect("Failed to get the profile. Did you log in using the correct account?")
 }
 
 fn prompt_auth() -> Result<User, Box<dyn std::error::Error>> {
 	let state = generate_state();
 
+	let auth = &config::get().auth;
 	let (auth_url, _, verifier_obj) =
-		get_secure_login_data(constants::CLIENT_ID, constants::REDIRECT_URL, Some(state));
+		get_secure_login_data(&auth.client_id, &auth.redirect_url, Some(state));
 	open::that(auth_url.as_str()).unwrap();
 	print!("Paste query string from URL (code=...&state=...):");
 	io::stdout().flush().unwrap();
@@ -449,17 +428,16 @@ fn prompt_auth() -> Result<User, Box<dyn std::error::Error>> {
 	let mut code = None;
 
 	for (k, v) in parsed {
-		match k.as_ref() {
-			"code" => code = Some(v.into_owned()),
-			_ => {}
+		if k.as_ref() == "code" {
+			code = Some(v.into_owned());
 		}
 	}
 
 	let rt = Runtime::new()?;
 	Ok(rt.block_on(async {
 		complete_login(
-			constants::CLIENT_ID,
-			constants::REDIRECT_URL,
+			&auth.client_id,
+			&auth.redirect_url,
 			code.unwrap(),
 			verifier_obj,
 		)
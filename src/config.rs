@@ -0,0 +1,594 @@
+use std::{
+	collections::HashSet,
+	fs, io,
+	path::{Path, PathBuf},
+	process::Command,
+	sync::OnceLock,
+};
+
+use serde::{Deserialize, Serialize};
+use toml_edit::{DocumentMut, Item};
+
+use crate::constants;
+use crate::helpers;
+use crate::instance::{self, Instance};
+
+/// Environment variable pointing at a config file to use instead of the default one.
+const CONFIG_ENV: &str = "VANTA_CONFIG";
+
+/// Every setting, as written in `vanta.toml`, `vanta config` and `-c key=value`.
+const KEYS: &[&str] = &[
+	"java",
+	"min_memory",
+	"max_memory",
+	"parallel",
+	"retries",
+	"data_dir",
+	"default_account",
+	"mirrors.meta",
+	"mirrors.data",
+	"mirrors.resources",
+	"mirrors.libraries",
+	"auth.client_id",
+	"auth.redirect_url",
+];
+
+/// Settings whose values are numbers rather than strings.
+const INTEGER_KEYS: &[&str] = &["parallel", "retries"];
+
+/// Written by `vanta config edit` when there is no config file yet.
+const TEMPLATE: &str = r#"# Vanta configuration. Every setting can also be given as an environment
+# variable (VANTA_PARALLEL, VANTA_MIRRORS_META, ...) or for a single run with
+# `vanta -c key=value`; those win over this file and over instance settings.
+
+# Java for instances that don't pick their own: a path, `17` or `>=17`.
+# Without it, the Mojang runtime each version asks for is used.
+# java = ">=17"
+
+# Heap sizes for instances that don't set their own.
+# min_memory = "2G"
+# max_memory = "4G"
+
+# Downloads at once, and attempts per file before giving up on it.
+# parallel = 4
+# retries = 5
+
+# Where instances, assets, libraries and runtimes are kept.
+# data_dir = "/path/to/vanta"
+
+# Account `vanta instance run` uses when none is given.
+# default_account = "Steve"
+
+[mirrors]
+# Base URLs replacing Mojang's servers, e.g. for a local cache.
+# meta = "https://piston-meta.mojang.com"
+# data = "https://piston-data.mojang.com"
+# resources = "https://resources.download.minecraft.net"
+# libraries = "https://libraries.minecraft.net"
+
+[auth]
+# Azure application used for Microsoft logins.
+# client_id = "..."
+# redirect_url = "..."
+"#;
+
+/// Launcher-wide settings: built-in defaults, overridden by `vanta.toml`, then
+/// by `VANTA_*` environment variables, then by `-c key=value` on the command line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+	/// Java of instances without their own choice, in the form instances use.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub java: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub min_memory: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub max_memory: Option<String>,
+	/// Downloads at once.
+	pub parallel: usize,
+	/// Attempts per file before giving up on it.
+	pub retries: usize,
+	/// Replaces the platform's data folder.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub data_dir: Option<PathBuf>,
+	/// Name of the account to launch with when none is given.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub default_account: Option<String>,
+	pub mirrors: Mirrors,
+	pub auth: Auth,
+	/// Keys set in the environment or on the command line, which beat instance settings.
+	#[serde(skip)]
+	forced: HashSet<String>,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			java: None,
+			min_memory: None,
+			max_memory: None,
+			parallel: 4,
+			retries: 5,
+			data_dir: None,
+			default_account: None,
+			mirrors: Mirrors::default(),
+			auth: Auth::default(),
+			forced: HashSet::new(),
+		}
+	}
+}
+
+/// Base URLs to fetch from instead of Mojang's own servers.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Mirrors {
+	/// Version manifests, version JSONs, asset indexes and runtime manifests.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub meta: Option<String>,
+	/// Client jars and runtime files.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub data: Option<String>,
+	/// Asset objects.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub resources: Option<String>,
+	/// Mojang's libraries.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub libraries: Option<String>,
+}
+
+impl Mirrors {
+	/// `url` with the Mojang server it points at swapped for its mirror, if one is set.
+	pub fn apply(&self, url: &str) -> String {
+		let servers = [
+			(
+				&self.meta,
+				&[
+					"https://piston-meta.mojang.com",
+					"https://launchermeta.mojang.com",
+				][..],
+			),
+			(
+				&self.data,
+				&[
+					"https://piston-data.mojang.com",
+					"https://launcher.mojang.com",
+				][..],
+			),
+			(
+				&self.resources,
+				&["https://resources.download.minecraft.net"][..],
+			),
+			(&self.libraries, &["https://libraries.minecraft.net"][..]),
+		];
+		for (mirror, origins) in servers {
+			let Some(mirror) = mirror else {
+				continue;
+			};
+			for origin in origins {
+				if let Some(rest) = url.strip_prefix(origin)
+					&& (rest.is_empty() || rest.starts_with('/'))
+				{
+					return format!("{}{}", mirror.trim_end_matches('/'), rest);
+				}
+			}
+		}
+		url.to_string()
+	}
+}
+
+/// The Azure application Microsoft logins go through.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Auth {
+	pub client_id: String,
+	pub redirect_url: String,
+}
+
+impl Default for Auth {
+	fn default() -> Self {
+		Self {
+			client_id: constants::CLIENT_ID.to_string(),
+			redirect_url: constants::REDIRECT_URL.to_string(),
+		}
+	}
+}
+
+impl Config {
+	/// Load the config file and apply environment and command line overrides.
+	pub fn load(overrides: &[String]) -> Result<Self, Box<dyn std::error::Error>> {
+		let path = file_path();
+		let mut doc = read_document(&path)?;
+
+		let mut forced = HashSet::new();
+		for key in KEYS {
+			if let Ok(value) = std::env::var(env_name(key)) {
+				set_key(&mut doc, key, parse_value(key, &value)?)?;
+				forced.insert(key.to_string());
+			}
+		}
+		for assignment in overrides {
+			let (key, value) = assignment
+				.split_once('=')
+				.ok_or(format!("Invalid override {}, use KEY=VALUE", assignment))?;
+			check_key(key)?;
+			set_key(&mut doc, key, parse_value(key, value)?)?;
+			forced.insert(key.to_string());
+		}
+
+		let mut config = Self::from_document(doc)?;
+		config.forced = forced;
+		Ok(config)
+	}
+
+	fn from_document(doc: DocumentMut) -> Result<Self, Box<dyn std::error::Error>> {
+		let config: Self = toml_edit::de::from_document(doc)
+			.map_err(|e| format!("Invalid config: {}", e.to_string().trim_end()))?;
+		config.validate()?;
+		Ok(config)
+	}
+
+	fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+		for (key, size) in [
+			("min_memory", &self.min_memory),
+			("max_memory", &self.max_memory),
+		] {
+			if let Some(size) = size
+				&& instance::memory_bytes(size).is_none()
+			{
+				return Err(format!(
+					"Invalid {} {}, use a number with K, M or G like 4G",
+					key, size
+				)
+				.into());
+			}
+		}
+		check_memory(self.min_memory.as_deref(), self.max_memory.as_deref())?;
+		if let Some(dir) = &self.data_dir
+			&& !dir.is_absolute()
+		{
+			return Err(format!("data_dir must be an absolute path, not {}", dir.display()).into());
+		}
+		if self.parallel == 0 || self.retries == 0 {
+			return Err("parallel and retries must be at least 1".into());
+		}
+		Ok(())
+	}
+
+	/// An instance's own setting for `key` over the global `value`, unless `key`
+	/// was given in the environment or on the command line.
+	fn layer<'a>(
+		&'a self,
+		key: &str,
+		own: &'a Option<String>,
+		value: &'a Option<String>,
+	) -> Option<&'a str> {
+		if self.forced.contains(key) {
+			value.as_deref()
+		} else {
+			own.as_deref().or(value.as_deref())
+		}
+	}
+
+	/// The Java `instance` runs with, in the form [`crate::java::JavaSpec`] parses.
+	pub fn java_for<'a>(&'a self, instance: &'a Instance) -> Option<&'a str> {
+		self.layer("java", &instance.java, &self.java)
+	}
+
+	/// Initial and maximum heap size of `instance`. Fails if layering leaves the
+	/// initial size above the maximum, which the JVM refuses to start with.
+	pub fn memory_for<'a>(
+		&'a self,
+		instance: &'a Instance,
+	) -> Result<HeapSizes<'a>, Box<dyn std::error::Error>> {
+		let min = self.layer("min_memory", &instance.min_memory, &self.min_memory);
+		let max = self.layer("max_memory", &instance.max_memory, &self.max_memory);
+		check_memory(min, max).map_err(|e| format!("Instance {}: {}", instance.id, e))?;
+		Ok((min, max))
+	}
+}
+
+/// Initial and maximum heap size, as given in the config.
+pub type HeapSizes<'a> = (Option<&'a str>, Option<&'a str>);
+
+fn check_memory(min: Option<&str>, max: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+	if let (Some(min), Some(max)) = (min, max)
+		&& instance::memory_bytes(min) > instance::memory_bytes(max)
+	{
+		return Err(format!("Minimum memory {} is above the maximum {}", min, max).into());
+	}
+	Ok(())
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Load the config for this run. Must come before anything reads it with [`get`].
+pub fn init(overrides: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+	init_with(Config::load(overrides)?);
+	Ok(())
+}
+
+/// Use `config` for this run instead of loading it, e.g. the defaults when the
+/// file is broken. Only the first call has an effect.
+pub fn init_with(config: Config) {
+	_ = CONFIG.set(config);
+}
+
+/// The config of this run, as set by [`init`] or [`init_with`].
+pub fn get() -> &'static Config {
+	if cfg!(test) {
+		// Tests run with the defaults, never the developer's own file, and keep
+		// their instances and store in a data folder of their own.
+		return CONFIG.get_or_init(|| Config {
+			data_dir: Some(std::env::temp_dir().join(format!("vanta-test-{}", std::process::id()))),
+			..Config::default()
+		});
+	}
+	CONFIG
+		.get()
+		.expect("the config is read before config::init")
+}
+
+/// `vanta.toml` in the platform's config folder, unless `VANTA_CONFIG` points elsewhere.
+pub fn file_path() -> PathBuf {
+	match std::env::var_os(CONFIG_ENV) {
+		Some(path) => PathBuf::from(path),
+		None => platform_dirs::AppDirs::new(Some("vanta"), true)
+			.unwrap()
+			.config_dir
+			.join("vanta.toml"),
+	}
+}
+
+/// `mirrors.meta` is read from `VANTA_MIRRORS_META`.
+fn env_name(key: &str) -> String {
+	format!("VANTA_{}", key.replace('.', "_").to_uppercase())
+}
+
+fn check_key(key: &str) -> Result<(), Box<dyn std::error::Error>> {
+	if KEYS.contains(&key) {
+		Ok(())
+	} else {
+		Err(format!("Unknown setting {}, choose one of {}", key, KEYS.join(", ")).into())
+	}
+}
+
+fn parse_value(key: &str, raw: &str) -> Result<Item, Box<dyn std::error::Error>> {
+	if INTEGER_KEYS.contains(&key) {
+		let number: i64 = raw
+			.trim()
+			.parse()
+			.map_err(|_| format!("{} must be a number, not {}", key, raw))?;
+		Ok(toml_edit::value(number))
+	} else {
+		Ok(toml_edit::value(raw))
+	}
+}
+
+/// Split `mirrors.meta` into its table and name.
+fn split_key(key: &str) -> (Option<&str>, &str) {
+	match key.split_once('.') {
+		Some((table, name)) => (Some(table), name),
+		None => (None, key),
+	}
+}
+
+fn set_key(
+	doc: &mut DocumentMut,
+	key: &str,
+	value: Item,
+) -> Result<(), Box<dyn std::error::Error>> {
+	match split_key(key) {
+		(Some(section), _) if doc.get(section).is_some_and(|s| !s.is_table_like()) => {
+			return Err(format!("{} is not a table", section).into());
+		}
+		(Some(section), name) => {
+			doc.entry(section).or_insert_with(toml_edit::table)[name] = value;
+		}
+		(None, name) => doc[name] = value,
+	}
+	Ok(())
+}
+
+fn read_document(path: &Path) -> Result<DocumentMut, Box<dyn std::error::Error>> {
+	match fs::read_to_string(path) {
+		Ok(raw) => raw.parse::<DocumentMut>().map_err(|e| {
+			format!("Invalid {}: {}", path.display(), e.to_string().trim_end()).into()
+		}),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(DocumentMut::new()),
+		Err(e) => Err(format!("Failed to read {}: {}", path.display(), e).into()),
+	}
+}
+
+/// Check an edited file and save it, keeping its comments and layout.
+fn write_document(path: &Path, doc: &DocumentMut) -> Result<(), Box<dyn std::error::Error>> {
+	let raw = doc.to_string();
+	Config::from_document(doc.clone())?;
+	helpers::write_bytes(path.to_string_lossy().to_string(), raw.as_bytes())
+}
+
+/// Print one effective setting, or all of them as TOML.
+pub fn print(key: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+	let Some(key) = key else {
+		print!("{}", toml_edit::ser::to_string_pretty(get())?);
+		return Ok(());
+	};
+	let config = toml_edit::ser::to_document(get())?;
+	check_key(key)?;
+	let value = match split_key(key) {
+		(Some(section), name) => config.get(section).and_then(|s| s.get(name)),
+		(None, name) => config.get(name),
+	};
+	match value.map(|item| (item.as_str(), item)) {
+		Some((Some(s), _)) => println!("{}", s),
+		Some((None, item)) => println!("{}", item.to_string().trim()),
+		None => (),
+	}
+	Ok(())
+}
+
+/// Change a setting in the config file.
+pub fn set(key: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+	check_key(key)?;
+	let path = file_path();
+	let mut doc = read_document(&path)?;
+	// Relative to where `set` runs, which is gone by the time the file is read.
+	let value = if key == "data_dir" {
+		std::path::absolute(value)?.to_string_lossy().to_string()
+	} else {
+		value.to_string()
+	};
+	set_key(&mut doc, key, parse_value(key, &value)?)
+		.map_err(|e| format!("{} in {}", e, path.display()))?;
+	write_document(&path, &doc)?;
+	println!("Set {} to {}.", key, value);
+	Ok(())
+}
+
+/// Remove a setting from the config file, going back to its default.
+pub fn unset(key: &str) -> Result<(), Box<dyn std::error::Error>> {
+	check_key(key)?;
+	let path = file_path();
+	let mut doc = read_document(&path)?;
+	let removed = match split_key(key) {
+		(Some(section), name) => doc
+			.get_mut(section)
+			.and_then(|s| s.as_table_like_mut())
+			.and_then(|s| s.remove(name)),
+		(None, name) => doc.remove(name),
+	};
+	if removed.is_none() {
+		println!("{} is not set.", key);
+		return Ok(());
+	}
+	write_document(&path, &doc)?;
+	println!("Unset {}.", key);
+	Ok(())
+}
+
+/// Open the config file in `$VISUAL` or `$EDITOR` and check it afterwards.
+pub fn edit() -> Result<(), Box<dyn std::error::Error>> {
+	let path = file_path();
+	if !path.exists() {
+		helpers::write_bytes(path.to_string_lossy().to_string(), TEMPLATE.as_bytes())?;
+	}
+
+	let editor = std::env::var("VISUAL")
+		.or_else(|_| std::env::var("EDITOR"))
+		.unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+	// Editors are often configured with arguments, like `code --wait`.
+	let mut words = editor.split_whitespace();
+	let program = words.next().ok_or("$EDITOR is empty")?;
+	let status = Command::new(program)
+		.args(words)
+		.arg(&path)
+		.status()
+		.map_err(|e| format!("Failed to start {}: {}", program, e))?;
+	if !status.success() {
+		return Err(format!("{} exited with {}", program, status).into());
+	}
+
+	let raw = fs::read_to_string(&path)?;
+	raw.parse::<DocumentMut>()
+		.map_err(|e| e.to_string().trim_end().into())
+		.and_then(Config::from_document)
+		.map_err(|e| {
+			format!(
+				"{} has errors, run `vanta config edit` again: {}",
+				path.display(),
+				e
+			)
+		})?;
+	println!("Saved {}.", path.display());
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn config(raw: &str) -> Result<Config, Box<dyn std::error::Error>> {
+		Config::from_document(raw.parse()?)
+	}
+
+	#[test]
+	fn template_is_the_defaults() {
+		let config = config(TEMPLATE).unwrap();
+		assert_eq!(config.parallel, 4);
+		assert_eq!(config.auth.client_id, constants::CLIENT_ID);
+		assert!(config.java.is_none() && config.mirrors.meta.is_none());
+		assert!(
+			get()
+				.data_dir
+				.as_ref()
+				.unwrap()
+				.starts_with(std::env::temp_dir())
+		);
+		assert!(get().java.is_none() && get().mirrors.libraries.is_none());
+	}
+
+	#[test]
+	fn rejects_bad_configs() {
+		assert!(config("parallel = 8\n[mirrors]\nmeta = \"https://m.example\"").is_ok());
+		assert!(config("paralel = 8").is_err());
+		assert!(config("parallel = \"8\"").is_err());
+		assert!(config("parallel = 0").is_err());
+		assert!(config("max_memory = \"lots\"").is_err());
+		assert!(config("min_memory = \"8G\"\nmax_memory = \"4096M\"").is_err());
+		assert!(config("[mirrors]\ncdn = \"x\"").is_err());
+		assert!(config("data_dir = \"games/vanta\"").is_err());
+	}
+
+	#[test]
+	fn layers_overrides() {
+		let mut doc: DocumentMut = "java = \"17\"\nparallel = 2".parse().unwrap();
+		set_key(&mut doc, "parallel", parse_value("parallel", "16").unwrap()).unwrap();
+		set_key(
+			&mut doc,
+			"mirrors.libraries",
+			parse_value("mirrors.libraries", "https://m.example/maven/").unwrap(),
+		)
+		.unwrap();
+		assert!(
+			set_key(
+				&mut doc,
+				"java.path",
+				parse_value("java.path", "x").unwrap()
+			)
+			.is_err()
+		);
+		let mut config = Config::from_document(doc).unwrap();
+		assert_eq!(config.parallel, 16);
+		assert_eq!(env_name("mirrors.libraries"), "VANTA_MIRRORS_LIBRARIES");
+		assert!(parse_value("retries", "many").is_err());
+
+		let mut instance = Instance::new("test", "1.20.1");
+		instance.java = Some(">=21".to_string());
+		assert_eq!(config.java_for(&instance), Some(">=21"));
+		config.forced.insert("java".to_string());
+		assert_eq!(config.java_for(&instance), Some("17"));
+		instance.max_memory = Some("8G".to_string());
+		assert_eq!(config.memory_for(&instance).unwrap(), (None, Some("8G")));
+		config.min_memory = Some("16G".to_string());
+		assert!(config.memory_for(&instance).is_err());
+		config.min_memory = None;
+
+		assert_eq!(
+			config
+				.mirrors
+				.apply("https://libraries.minecraft.net/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar"),
+			"https://m.example/maven/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar"
+		);
+		assert_eq!(
+			config
+				.mirrors
+				.apply("https://libraries.minecraft.net.evil/x"),
+			"https://libraries.minecraft.net.evil/x"
+		);
+		assert_eq!(
+			config
+				.mirrors
+				.apply("https://piston-data.mojang.com/v1/objects/x/client.jar"),
+			"https://piston-data.mojang.com/v1/objects/x/client.jar"
+		);
+	}
+}
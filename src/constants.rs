@@ -1,3 +1,4 @@
+/// Defaults of `auth.client_id` and `auth.redirect_url` in the config.
 pub const CLIENT_ID: &str = "708e91b5-99f8-4a1d-80ec-e746cbb24771";
 pub const REDIRECT_URL: &str = "https://www.theorozier.fr/portablemc/auth";
 
@@ -1,48 +1,309 @@
-use std::fs;
-use std::path::PathBuf;
-use std::thread;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use tokio::io::AsyncWriteExt;
 use tokio::runtime::Runtime;
 
+use crate::config;
 use crate::helpers;
-use crate::helpers::get_instance_folder;
+use crate::helpers::{get_assets_folder, get_data_folder, get_libraries_folder};
+use crate::instance::{self, Instance};
+use crate::progress::Progress;
+use crate::rules::{Environment, Features, rules_allow};
+use crate::runtime;
+use crate::version::{self, Artifact, Library, VersionJson};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 
 use std::vec;
 
-use reqwest::Client;
+use futures::stream::{self, StreamExt};
+use rand::Rng;
+use reqwest::header::RANGE;
+use reqwest::{Client, StatusCode};
+use sha1::{Digest, Sha1};
 
-pub fn download(
-	entry: &mut DownloadEntry,
-	prefix: PathBuf,
-) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-	let resp = reqwest::blocking::get(entry.url.clone())?;
-	let bytes = &resp.bytes()?;
-	helpers::write_bytes(
-		prefix
-			.join(entry.destination.clone())
-			.to_string_lossy()
-			.to_string(),
-		&bytes.clone(),
-	)?;
+/// How often and how patiently a failing download is retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+	/// Total attempts per file, including the first one.
+	pub attempts: usize,
+	pub base_delay: Duration,
+	pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self {
+			attempts: 5,
+			base_delay: Duration::from_millis(500),
+			max_delay: Duration::from_secs(30),
+		}
+	}
+}
+
+impl RetryPolicy {
+	pub fn with_attempts(attempts: usize) -> Self {
+		Self {
+			attempts: attempts.max(1),
+			..Self::default()
+		}
+	}
+
+	/// Exponential backoff before retry number `attempt` (starting at 1), with
+	/// up to 50% jitter so parallel downloads don't hammer the server in lockstep.
+	pub fn delay(&self, attempt: usize) -> Duration {
+		let exp = self
+			.base_delay
+			.saturating_mul(1 << attempt.saturating_sub(1).min(16))
+			.min(self.max_delay);
+		exp.mul_f64(rand::rng().random_range(0.5..=1.0))
+	}
+}
+
+/// Why an attempt failed, which decides what happens to the `.part` file.
+enum FetchError {
+	/// Network trouble: keep what we have and resume from it next time.
+	Retry(String),
+	/// The data itself is bad: throw the partial file away.
+	Corrupt(String),
+	/// Retrying won't help (e.g. 404).
+	Fatal(String),
+}
 
-	Ok(bytes.to_vec())
+impl FetchError {
+	fn message(&self) -> &str {
+		match self {
+			FetchError::Retry(m) | FetchError::Corrupt(m) | FetchError::Fatal(m) => m,
+		}
+	}
+}
+
+/// The temporary file a download is streamed into before it is verified.
+pub fn part_path(dest: &Path) -> PathBuf {
+	let mut name = dest.file_name().unwrap_or_default().to_os_string();
+	name.push(".part");
+	dest.with_file_name(name)
+}
+
+/// Download an entry to its [`entry_path`] for the instance at `prefix`. The body
+/// is streamed into a `.part` file that is only moved into place once its size
+/// and SHA-1 match the entry. Failed attempts are retried with backoff and
+/// resume from the partial file with an HTTP `Range` request where possible.
+pub async fn download(
+	client: &Client,
+	entry: &DownloadEntry,
+	prefix: &Path,
+	retry: &RetryPolicy,
+	progress: &Progress,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let dest = entry_path(entry, prefix);
+	let part = part_path(&dest);
+	if let Some(p) = dest.parent() {
+		tokio::fs::create_dir_all(p).await?;
+	}
+
+	let mut last_err = String::new();
+	for attempt in 0..retry.attempts {
+		if attempt > 0 {
+			tokio::time::sleep(retry.delay(attempt)).await;
+		}
+		match fetch_to_file(client, entry, &part, progress).await {
+			Ok(()) => {
+				tokio::fs::rename(&part, &dest).await?;
+				return Ok(());
+			}
+			Err(e) => {
+				last_err = e.message().to_string();
+				match e {
+					FetchError::Retry(_) => {}
+					FetchError::Corrupt(_) => _ = tokio::fs::remove_file(&part).await,
+					FetchError::Fatal(_) => break,
+				}
+			}
+		}
+	}
+
+	Err(last_err.into())
+}
+
+/// SHA-1 state of a partial file, so a resumed download can keep hashing where it left off.
+async fn hash_part(path: &Path) -> Result<Sha1, FetchError> {
+	let path = path.to_path_buf();
+	tokio::task::spawn_blocking(move || {
+		let mut hasher = Sha1::new();
+		io::copy(&mut File::open(&path)?, &mut hasher)?;
+		Ok::<_, io::Error>(hasher)
+	})
+	.await
+	.map_err(|e| FetchError::Retry(e.to_string()))?
+	.map_err(|e| FetchError::Retry(e.to_string()))
+}
+
+async fn fetch_to_file(
+	client: &Client,
+	entry: &DownloadEntry,
+	path: &Path,
+	progress: &Progress,
+) -> Result<(), FetchError> {
+	let io_err = |e: io::Error| FetchError::Retry(e.to_string());
+	let net_err = |e: reqwest::Error| FetchError::Retry(e.to_string());
+
+	let existing = tokio::fs::metadata(path).await.map_or(0, |m| m.len());
+	let resume = existing > 0 && entry.size.is_some_and(|s| existing < s as u64);
+	if existing > 0 && !resume {
+		tokio::fs::remove_file(path).await.map_err(io_err)?;
+	}
+
+	let mut req = client.get(config::get().mirrors.apply(&entry.url));
+	if resume {
+		req = req.header(RANGE, format!("bytes={}-", existing));
+	}
+	let mut resp = req.send().await.map_err(net_err)?;
+
+	let status = resp.status();
+	if status == StatusCode::RANGE_NOT_SATISFIABLE {
+		return Err(FetchError::Corrupt(format!(
+			"{} for resumed download",
+			status
+		)));
+	}
+	if status.is_client_error()
+		&& status != StatusCode::REQUEST_TIMEOUT
+		&& status != StatusCode::TOO_MANY_REQUESTS
+	{
+		return Err(FetchError::Fatal(format!("{} for {}", status, entry.url)));
+	}
+	if !status.is_success() {
+		return Err(FetchError::Retry(format!("{} for {}", status, entry.url)));
+	}
+
+	// A server that ignores `Range` answers 200 with the whole body; start over then.
+	let (mut file, mut hasher, mut written) = if resume && status == StatusCode::PARTIAL_CONTENT {
+		let hasher = hash_part(path).await?;
+		let file = tokio::fs::OpenOptions::new()
+			.append(true)
+			.open(path)
+			.await
+			.map_err(io_err)?;
+		(file, hasher, existing as usize)
+	} else {
+		let file = tokio::fs::File::create(path).await.map_err(io_err)?;
+		(file, Sha1::new(), 0)
+	};
+
+	while let Some(chunk) = resp.chunk().await.map_err(net_err)? {
+		hasher.update(&chunk);
+		file.write_all(&chunk).await.map_err(io_err)?;
+		written += chunk.len();
+		progress.update(entry, written as u64);
+	}
+	file.sync_all().await.map_err(io_err)?;
+
+	check_digest(entry, written, &format!("{:x}", hasher.finalize())).map_err(|e| {
+		match entry.size {
+			// Connection closed early: keep the bytes and resume next attempt.
+			Some(size) if written < size => FetchError::Retry(e),
+			_ => FetchError::Corrupt(e),
+		}
+	})
+}
+
+fn check_digest(entry: &DownloadEntry, len: usize, sha1: &str) -> Result<(), String> {
+	if let Some(size) = entry.size
+		&& len != size
+	{
+		return Err(format!("expected {} bytes, got {}", size, len));
+	}
+	if let Some(expected) = &entry.sha1
+		&& !sha1.eq_ignore_ascii_case(expected)
+	{
+		return Err(format!(
+			"SHA-1 mismatch (expected {}, got {})",
+			expected, sha1
+		));
+	}
+	Ok(())
+}
+
+/// SHA-1 of a file on disk as a lowercase hex string.
+pub fn sha1_file(path: &Path) -> io::Result<String> {
+	let mut hasher = Sha1::new();
+	io::copy(&mut File::open(path)?, &mut hasher)?;
+	Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+	Ok,
+	Missing,
+	WrongSize,
+	Corrupt,
+}
+
+impl FileStatus {
+	pub fn label(&self) -> &'static str {
+		match self {
+			FileStatus::Ok => "OK",
+			FileStatus::Missing => "MISSING",
+			FileStatus::WrongSize => "SIZE",
+			FileStatus::Corrupt => "CORRUPT",
+		}
+	}
+}
+
+/// Where the file for `entry` lives: the shared store for assets and
+/// libraries, the instance at `prefix` for everything else.
+pub fn entry_path(entry: &DownloadEntry, prefix: &Path) -> PathBuf {
+	if entry.shared {
+		get_data_folder(Some(&entry.destination))
+	} else {
+		prefix.join(&entry.destination)
+	}
+}
+
+/// Check the file for `entry` for presence, size and SHA-1.
+pub fn check_entry(entry: &DownloadEntry, prefix: &Path) -> FileStatus {
+	let path = entry_path(entry, prefix);
+	let Ok(meta) = fs::metadata(&path) else {
+		return FileStatus::Missing;
+	};
+	if entry.size.is_some_and(|s| meta.len() != s as u64) {
+		return FileStatus::WrongSize;
+	}
+	match &entry.sha1 {
+		Some(expected) if !sha1_file(&path).is_ok_and(|h| h.eq_ignore_ascii_case(expected)) => {
+			FileStatus::Corrupt
+		}
+		_ => FileStatus::Ok,
+	}
+}
+
+/// Whether the file for `entry` already exists with the expected size and hash.
+pub fn entry_is_valid(entry: &DownloadEntry, prefix: &Path) -> bool {
+	check_entry(entry, prefix) == FileStatus::Ok
 }
 
 pub async fn get_version_manifest() -> Result<VersionManifest, Box<dyn std::error::Error>> {
 	let client = Client::new();
 	let raw_resp = client
-		.get("https://launchermeta.mojang.com/mc/game/version_manifest_v2.json")
+		.get(
+			config::get()
+				.mirrors
+				.apply("https://launchermeta.mojang.com/mc/game/version_manifest_v2.json"),
+		)
 		.send()
 		.await?
 		.error_for_status()?
 		.text()
 		.await?;
 
-	let resp = serde_json::from_str::<VersionManifest>(&raw_resp).unwrap();
+	let resp = serde_json::from_str::<VersionManifest>(&raw_resp)
+		.map_err(|e| format!("Invalid version manifest: {}", e))?;
 
 	Ok(resp)
 }
@@ -50,38 +311,83 @@ pub async fn get_version_manifest() -> Result<VersionManifest, Box<dyn std::erro
 pub async fn get_version_json(url: String) -> Result<VersionJson, Box<dyn std::error::Error>> {
 	let client = Client::new();
 	let raw_resp = client
-		.get(&url)
+		.get(config::get().mirrors.apply(&url))
 		.send()
 		.await?
 		.error_for_status()?
 		.text()
 		.await?;
 
-	let resp = serde_json::from_str::<VersionJson>(&raw_resp).unwrap();
-
-	Ok(resp)
+	VersionJson::parse(&raw_resp).map_err(|e| format!("{} ({})", e, url).into())
 }
 
-pub async fn get_assets_vec(url: String) -> Result<MinecraftAssets, Box<dyn std::error::Error>> {
+pub async fn get_asset_index_raw(url: &str) -> Result<String, Box<dyn std::error::Error>> {
 	let client = Client::new();
 	let raw_resp = client
-		.get(&url)
+		.get(config::get().mirrors.apply(url))
 		.send()
 		.await?
 		.error_for_status()?
 		.text()
 		.await?;
 
-	let resp = serde_json::from_str::<MinecraftAssets>(&raw_resp).unwrap();
+	Ok(raw_resp)
+}
 
-	Ok(resp)
+pub fn asset_index_id(version: &VersionJson) -> &str {
+	version
+		.asset_index
+		.as_ref()
+		.map_or("legacy", |a| a.id.as_str())
+}
+
+/// Where the game looks for a version's asset index: `assets/indexes/<id>.json`.
+pub fn asset_index_path(version: &VersionJson) -> PathBuf {
+	get_assets_folder()
+		.join("indexes")
+		.join(format!("{}.json", asset_index_id(version)))
+}
+
+/// Load a version's asset index, reusing the saved index file while its hash
+/// still matches and downloading (and saving) it otherwise.
+pub async fn load_asset_index(
+	version: &VersionJson,
+) -> Result<MinecraftAssets, Box<dyn std::error::Error>> {
+	let path = asset_index_path(version);
+	let index = version
+		.asset_index
+		.as_ref()
+		.ok_or("Version JSON has no asset index")?;
+	let expected = index.sha1.as_deref();
+	let cached = path.exists()
+		&& expected.is_none_or(|h| sha1_file(&path).is_ok_and(|a| a.eq_ignore_ascii_case(h)));
+
+	if !cached {
+		let url = index
+			.url
+			.as_deref()
+			.ok_or("Version JSON has no asset index URL")?;
+		let raw = get_asset_index_raw(url).await?;
+		helpers::write_bytes(path.to_string_lossy().to_string(), raw.as_bytes())?;
+	}
+
+	read_asset_index(version)
+}
+
+/// Read the saved asset index of a version without touching the network.
+pub fn read_asset_index(
+	version: &VersionJson,
+) -> Result<MinecraftAssets, Box<dyn std::error::Error>> {
+	let path = asset_index_path(version);
+	let raw = fs::read_to_string(&path)
+		.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+	Ok(serde_json::from_str::<MinecraftAssets>(&raw)?)
 }
 
 pub async fn queue_assets(
 	version: &VersionJson,
 ) -> Result<Vec<DownloadEntry>, Box<dyn std::error::Error>> {
-	let assets: MinecraftAssets =
-		get_assets_vec(version.assetIndex["url"].as_str().unwrap().to_string()).await?;
+	let assets = load_asset_index(version).await?;
 	let mut queue: Vec<DownloadEntry> = vec![];
 
 	for (k, a) in assets.objects.iter() {
@@ -93,35 +399,200 @@ pub async fn queue_assets(
 			),
 			destination: format!("assets/objects/{}/{}", &a.hash[0..2], a.hash),
 			size: Some(a.size),
-			sha1: None,
+			sha1: Some(a.hash.clone()),
 			name: Some(k.to_string()),
-			executable: false,
+			shared: true,
 		});
 	}
 
 	Ok(queue)
 }
 
+/// The directory old versions read assets from by name, if their index asks
+/// for one: `assets/virtual/<id>/` for `virtual` indexes (1.6 - 1.7.2) and
+/// `<game dir>/resources/` for `map_to_resources` ones (pre-1.6).
+pub fn legacy_assets_dir(
+	version: &VersionJson,
+	assets: &MinecraftAssets,
+	game_dir: &Path,
+) -> Option<PathBuf> {
+	if assets.map_to_resources {
+		Some(game_dir.join("resources"))
+	} else if assets.is_virtual {
+		Some(
+			get_assets_folder()
+				.join("virtual")
+				.join(asset_index_id(version)),
+		)
+	} else {
+		None
+	}
+}
+
+/// Copy asset objects to their logical names for versions with a legacy asset index.
+pub fn lay_out_legacy_assets(
+	version: &VersionJson,
+	game_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let assets = read_asset_index(version)?;
+	let Some(dir) = legacy_assets_dir(version, &assets, game_dir) else {
+		return Ok(());
+	};
+	let objects = get_assets_folder().join("objects");
+
+	for (name, a) in assets.objects.iter() {
+		let dest = dir.join(name);
+		if fs::metadata(&dest).is_ok_and(|m| m.len() == a.size as u64) {
+			continue;
+		}
+		if let Some(p) = dest.parent() {
+			fs::create_dir_all(p)?;
+		}
+		fs::copy(objects.join(&a.hash[0..2]).join(&a.hash), &dest)
+			.map_err(|e| format!("Failed to copy asset {}: {}", name, e))?;
+	}
+
+	Ok(())
+}
+
 pub async fn queue_libs(
 	version: &VersionJson,
 ) -> Result<Vec<DownloadEntry>, Box<dyn std::error::Error>> {
+	Ok(library_downloads(&version.libraries, &Environment::host()))
+}
+
+/// Whether a library entry applies to `env` according to its `rules`.
+pub fn library_applies(lib: &Library, env: &Environment) -> bool {
+	rules_allow(&lib.rules, env, &Features::new())
+}
+
+/// Path of a Maven artifact below a repository root, from its coordinate
+/// `group:artifact:version[:classifier][@extension]`.
+pub fn maven_path(coordinate: &str) -> Option<String> {
+	let (coordinate, extension) = coordinate.split_once('@').unwrap_or((coordinate, "jar"));
+	let parts: Vec<&str> = coordinate.split(':').collect();
+	let (group, artifact, version, classifier) = match parts.as_slice() {
+		[g, a, v] => (g, a, v, None),
+		[g, a, v, c] => (g, a, v, Some(c)),
+		_ => return None,
+	};
+	let file = match classifier {
+		Some(c) => format!("{}-{}-{}.{}", artifact, version, c, extension),
+		None => format!("{}-{}.{}", artifact, version, extension),
+	};
+	Some(format!(
+		"{}/{}/{}/{}",
+		group.replace('.', "/"),
+		artifact,
+		version,
+		file
+	))
+}
+
+/// The main jar of a library: its `downloads.artifact`, or for libraries that
+/// only name a Maven repository, the artifact resolved from its coordinate.
+pub fn library_artifact(lib: &Library) -> Option<Artifact> {
+	if let Some(downloads) = &lib.downloads {
+		return downloads.artifact.clone();
+	}
+	let path = maven_path(&lib.name)?;
+	let repo = lib
+		.url
+		.as_deref()
+		.unwrap_or("https://libraries.minecraft.net/");
+	Some(Artifact {
+		url: format!("{}/{}", repo.trim_end_matches('/'), path),
+		path: Some(path),
+		sha1: lib.sha1.clone(),
+		size: lib.size,
+	})
+}
+
+/// The classifier holding a library's natives for `env`, e.g. `natives-windows-64`.
+pub fn native_classifier(lib: &Library, env: &Environment) -> Option<String> {
+	lib.natives
+		.get(&env.os_name)
+		.map(|c| c.replace("${arch}", env.arch_bits()))
+}
+
+/// The host's natives jar of a library, if it has one.
+fn native_artifact<'a>(lib: &'a Library, env: &Environment) -> Option<&'a Artifact> {
+	let classifier = native_classifier(lib, env)?;
+	lib.downloads.as_ref()?.classifiers.get(&classifier)
+}
+
+fn library_entry(dl: &Artifact) -> Option<DownloadEntry> {
+	let path = dl.path.as_deref()?;
+	if dl.url.is_empty() {
+		return None;
+	}
+	Some(DownloadEntry {
+		size: dl.size.map(|s| s as usize),
+		destination: format!("libraries/{}", path),
+		name: Some(path.to_string()),
+		url: dl.url.clone(),
+		sha1: dl.sha1.clone(),
+		shared: true,
+	})
+}
+
+/// Every library file `env` needs: the main artifact of each applicable
+/// library, plus the host's native classifier jar where there is one.
+pub fn library_downloads(libraries: &[Library], env: &Environment) -> Vec<DownloadEntry> {
 	let mut queue: Vec<DownloadEntry> = vec![];
 
-	for l in version.libraries.iter() {
-		// TODO: check for OS to make sure we aren't downloading
-		// anything we dont need
-		let dl = l["downloads"]["artifact"].clone();
-		queue.push(DownloadEntry {
-			size: Some(dl["size"].as_u64().unwrap() as usize),
-			destination: format!("libraries/{}", &dl["path"].as_str().unwrap()),
-			name: Some(dl["path"].as_str().unwrap().to_string()),
-			url: dl["url"].as_str().unwrap().to_string(),
-			sha1: Some(dl["sha1"].as_str().unwrap().to_string()),
-			executable: false,
-		})
+	for l in libraries.iter().filter(|l| library_applies(l, env)) {
+		if let Some(entry) = library_artifact(l).as_ref().and_then(library_entry) {
+			queue.push(entry);
+		}
+		if let Some(entry) = native_artifact(l, env).and_then(library_entry) {
+			queue.push(entry);
+		}
 	}
 
-	Ok(queue)
+	queue
+}
+
+/// Unpack the host's native classifier jars into `versions/natives/`,
+/// skipping any paths listed in the library's `extract.exclude`.
+pub fn extract_natives(
+	version: &VersionJson,
+	inst_dir: &Path,
+	env: &Environment,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let natives_dir = inst_dir.join("versions/natives");
+	fs::create_dir_all(&natives_dir)?;
+
+	for l in version.libraries.iter().filter(|l| library_applies(l, env)) {
+		let Some(path) = native_artifact(l, env).and_then(|a| a.path.as_deref()) else {
+			continue;
+		};
+		let exclude: &[String] = l.extract.as_ref().map_or(&[], |e| &e.exclude);
+
+		let jar = get_libraries_folder().join(path);
+		let mut archive = File::open(&jar)
+			.map_err(|e| e.to_string())
+			.and_then(|f| zip::ZipArchive::new(f).map_err(|e| e.to_string()))
+			.map_err(|e| format!("Failed to open {}: {}", jar.display(), e))?;
+
+		for i in 0..archive.len() {
+			let mut file = archive.by_index(i)?;
+			let entry_name = file.name()?.to_string();
+			if file.is_dir() || exclude.iter().any(|e| entry_name.starts_with(e)) {
+				continue;
+			}
+			let Some(name) = file.enclosed_name() else {
+				continue;
+			};
+			let dest = natives_dir.join(name);
+			if let Some(p) = dest.parent() {
+				fs::create_dir_all(p)?;
+			}
+			io::copy(&mut file, &mut File::create(&dest)?)?;
+		}
+	}
+
+	Ok(())
 }
 
 #[derive(Debug, Clone)]
@@ -131,43 +602,22 @@ pub struct DownloadEntry {
 	pub size: Option<usize>,
 	pub sha1: Option<String>,
 	pub name: Option<String>,
-	pub executable: bool,
+	/// Stored once in the shared store under the data folder rather than per instance.
+	pub shared: bool,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct VersionManifest {
-	pub latest: VersionManifestLatest,
 	pub versions: Vec<VersionManifestVersion>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct VersionManifestLatest {
-	pub release: String,
-	pub snapshot: String,
-}
-
+#[allow(non_snake_case)]
 #[derive(Debug, Deserialize)]
 pub struct VersionManifestVersion {
 	pub id: String,
 	pub r#type: String,
 	pub url: String,
-	pub time: String,
-	#[allow(non_snake_case)]
 	pub releaseTime: String,
-	pub sha1: String,
-	#[allow(non_snake_case)]
-	pub complianceLevel: i8,
-}
-
-#[derive(Debug, Deserialize, Serialize)]
-pub struct VersionJson {
-	pub arguments: serde_json::Value,
-	#[allow(non_snake_case)]
-	pub assetIndex: serde_json::Value,
-	pub downloads: serde_json::Value,
-	pub libraries: Vec<serde_json::Value>,
-	#[serde(flatten)]
-	pub extra: serde_json::Value,
 }
 
 #[derive(Debug, Deserialize)]
@@ -179,97 +629,273 @@ pub struct MinecraftAsset {
 #[derive(Debug, Deserialize)]
 pub struct MinecraftAssets {
 	pub objects: HashMap<String, MinecraftAsset>,
+	/// Objects must also be available by name under `assets/virtual/<id>/`.
+	#[serde(default, rename = "virtual")]
+	pub is_virtual: bool,
+	/// Objects must also be available by name under `<game dir>/resources/`.
+	#[serde(default)]
+	pub map_to_resources: bool,
+}
+
+/// Create an instance of `version`. It is installed into a hidden staging
+/// folder that only takes the instance's place once everything succeeded, so
+/// a failed install leaves nothing behind.
+pub fn new_instance(
+	version: String,
+	id: String,
+	parallel: usize,
+	retries: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+	create_instance(&version, &id, |staging| {
+		install_minecraft(&version, staging, parallel, retries)
+	})?;
+
+	println!("Created instance {} ({}).", id, version);
+	Ok(())
 }
 
-pub fn new_instance(version: String, id: String, dl_threads: usize) {
-	let folder = get_instance_folder(&version);
-	_ = fs::create_dir_all(folder);
-	_ = install_minecraft(version, id, dl_threads);
+/// Run `install` on a locked staging folder and move the result into place
+/// as instance `id`, removing the staging folder if any step fails.
+fn create_instance(
+	version: &str,
+	id: &str,
+	install: impl FnOnce(&Path) -> Result<(), Box<dyn std::error::Error>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+	instance::validate_new_id(id)?;
+	let (staging, _lock) = instance::lock_staging(id)?;
+
+	let result = install(&staging)
+		.and_then(|()| Instance::new(id, version).save_in(&staging))
+		// Someone may have taken the id while we were downloading.
+		.and_then(|()| instance::move_into_place(&staging, id));
+	if result.is_err() {
+		_ = fs::remove_dir_all(&staging);
+	}
+	result
 }
 
+/// Install the vanilla game files of `version` into an instance folder.
 pub fn install_minecraft(
-	version: String,
-	instance: String,
-	threads: usize,
+	version: &str,
+	inst_dir: &Path,
+	parallel: usize,
+	retries: usize,
 ) -> Result<(), Box<dyn std::error::Error>> {
 	let rt = Runtime::new()?;
-	let versions = rt.block_on(async { get_version_manifest().await }).unwrap();
 
-	let valid_version = match versions.versions.iter().find(|v| v.id == version) {
-		Some(v) => v,
-		None => panic!("Invalid Version"),
-	};
+	let version_json = rt.block_on(async {
+		let versions = get_version_manifest().await?;
+		let valid_version = versions
+			.versions
+			.iter()
+			.find(|v| v.id == version)
+			.ok_or(format!("Unknown version {}", version))?;
+		get_version_json(valid_version.url.clone()).await
+	})?;
 
-	let version_json = rt
-		.block_on(async { get_version_json(valid_version.url.clone()).await })
-		.unwrap();
+	let queue = rt.block_on(async { queue_all(&version_json).await })?;
 
-	let mut queue: Vec<DownloadEntry> = vec![];
+	helpers::write_bytes(
+		inst_dir
+			.join(version::CLIENT_JSON)
+			.to_string_lossy()
+			.to_string(),
+		serde_json::to_string(&version_json)?.as_bytes(),
+	)?;
 
-	let asset_queue = rt
-		.block_on(async { queue_assets(&version_json).await })
-		.unwrap();
+	download_queue(&rt, queue, inst_dir, parallel, retries)?;
 
-	let lib_queue = rt
-		.block_on(async { queue_libs(&version_json).await })
-		.unwrap();
+	extract_natives(&version_json, inst_dir, &Environment::host())?;
+	lay_out_legacy_assets(&version_json, inst_dir)?;
 
-	queue.extend(asset_queue);
-	queue.extend(lib_queue);
-	queue.push(DownloadEntry {
-		url: version_json.downloads["client"]["url"]
-			.as_str()
-			.unwrap()
-			.to_string(),
+	if runtime::provision(&rt, &version_json, parallel, retries)?.is_none() {
+		println!("Mojang has no Java runtime for this platform, the system's java will be used.");
+	}
+
+	Ok(())
+}
+
+/// The client jar of a version.
+pub fn client_entry(version: &VersionJson) -> Option<DownloadEntry> {
+	let client = version.downloads.get("client")?;
+	Some(DownloadEntry {
+		url: client.url.clone(),
 		destination: "versions/client.jar".to_string(),
-		size: Some(version_json.downloads["client"]["size"].as_u64().unwrap() as usize),
-		sha1: Some("".to_string()),
+		size: client.size.map(|s| s as usize),
+		sha1: client.sha1.clone(),
 		name: Some("Client".to_string()),
-		executable: true,
-	});
-
-	_ = helpers::write_bytes(
-		PathBuf::from(get_instance_folder(&instance).clone())
-			.join("versions/client.json")
-			.to_str()
-			.unwrap()
-			.to_string(),
-		serde_json::to_string(&version_json).unwrap().as_bytes(),
-	);
+		shared: false,
+	})
+}
 
-	let qlen = queue.len();
-	let chunk_size = (qlen + threads - 1) / threads;
-
-	let handles: Vec<_> = (0..threads)
-		.map(|i| {
-			let mut queue_chunk: Vec<_> =
-				queue[i * chunk_size..std::cmp::min((i + 1) * chunk_size, qlen)].to_vec(); // clones entries
-
-			let inst_id = instance.clone();
-			thread::spawn(move || {
-				for mut e in queue_chunk.iter_mut() {
-					println!(
-						"Downloading {} ({} bytes)",
-						e.name.clone().map_or("_".to_string(), |v| v),
-						e.size.map_or(0, |v| v)
-					);
-
-					if let Err(err) = download(&mut e, get_instance_folder(&inst_id)) {
-						eprintln!(
-							"Failed to download {}: {}",
-							e.name.clone().unwrap_or("_".to_string()),
-							err
-						);
-					}
-				}
-			})
+/// Every file an instance of `version` needs: assets, libraries and the client jar.
+pub async fn queue_all(
+	version: &VersionJson,
+) -> Result<Vec<DownloadEntry>, Box<dyn std::error::Error>> {
+	let mut queue: Vec<DownloadEntry> = vec![];
+
+	queue.extend(queue_assets(version).await?);
+	queue.extend(queue_libs(version).await?);
+	queue.push(client_entry(version).ok_or("Version JSON has no client download")?);
+
+	Ok(queue)
+}
+
+/// Drop entries that would be written to the same file as an earlier one, e.g.
+/// asset names sharing a hash, so no two downloads race on one `.part` file.
+pub fn dedup_queue(queue: Vec<DownloadEntry>, prefix: &Path) -> Vec<DownloadEntry> {
+	let mut seen = HashSet::new();
+	queue
+		.into_iter()
+		.filter(|e| seen.insert(entry_path(e, prefix)))
+		.collect()
+}
+
+/// Download every entry that isn't already valid on disk, at most `parallel` at
+/// a time over one shared client. The queue must have been through
+/// [`dedup_queue`]. Returns the entries that failed and why.
+pub async fn download_all(
+	client: &Client,
+	queue: Vec<DownloadEntry>,
+	prefix: &Path,
+	parallel: usize,
+	retry: &RetryPolicy,
+	progress: &Progress,
+) -> Vec<(DownloadEntry, String)> {
+	stream::iter(queue)
+		.map(|e| async move {
+			let (check, check_prefix) = (e.clone(), prefix.to_path_buf());
+			let valid = tokio::task::spawn_blocking(move || entry_is_valid(&check, &check_prefix))
+				.await
+				.unwrap_or(false);
+			if valid {
+				progress.skip(&e);
+				return None;
+			}
+
+			let result = download(client, &e, prefix, retry, progress).await;
+			progress.finish_file(&e, result.is_ok());
+			match result {
+				Ok(()) => None,
+				Err(err) => Some((e, err.to_string())),
+			}
 		})
-		.collect();
+		.buffer_unordered(parallel.max(1))
+		.filter_map(|r| async move { r })
+		.collect()
+		.await
+}
 
-	for handle in handles {
-		_ = handle.join();
+/// Download a queue with a progress display and report anything that failed.
+pub fn download_queue(
+	rt: &Runtime,
+	queue: Vec<DownloadEntry>,
+	prefix: &Path,
+	parallel: usize,
+	retries: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let client = Client::new();
+	let retry = RetryPolicy::with_attempts(retries);
+	let queue = dedup_queue(queue, prefix);
+	let progress = Progress::new(&queue);
+	let failures = rt.block_on(download_all(
+		&client, queue, prefix, parallel, &retry, &progress,
+	));
+	progress.finish();
+	report_failures(&failures)
+}
+
+/// Print every download that failed for good and turn them into one error.
+pub fn report_failures(
+	failures: &[(DownloadEntry, String)],
+) -> Result<(), Box<dyn std::error::Error>> {
+	if failures.is_empty() {
+		return Ok(());
+	}
+	eprintln!("Failed to download {} files:", failures.len());
+	for (e, err) in failures {
+		eprintln!(
+			"  {}: {}",
+			e.name.clone().unwrap_or(e.destination.clone()),
+			err
+		);
+	}
+	Err(format!("{} files failed to download", failures.len()).into())
+}
+
+/// Check every file of an instance against its version JSON and print what is
+/// missing or corrupt. With `fix`, only those files are downloaded again.
+pub fn verify_instance(
+	id: String,
+	fix: bool,
+	parallel: usize,
+	retries: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let instance = Instance::load(&id)?;
+	let inst_dir = instance.dir();
+	let version_json = version::read_instance_version(&inst_dir)
+		.map_err(|e| format!("Instance {} has no usable version JSON: {}", id, e))?;
+
+	let rt = Runtime::new()?;
+	let queue = dedup_queue(
+		rt.block_on(async { queue_all(&version_json).await })?,
+		&inst_dir,
+	);
+	let total = queue.len();
+
+	let mut broken: Vec<DownloadEntry> = vec![];
+	for e in queue {
+		let status = check_entry(&e, &inst_dir);
+		if status != FileStatus::Ok {
+			println!("{:<8} {}", status.label(), e.destination);
+			broken.push(e);
+		}
+	}
+
+	if broken.is_empty() {
+		println!("All {} files are present and valid.", total);
+		return Ok(());
+	}
+
+	println!(
+		"{} of {} files are missing or corrupt.",
+		broken.len(),
+		total
+	);
+	if !fix {
+		return Err("Instance is damaged, run again with --fix to repair it".into());
 	}
 
+	let client = Client::new();
+	let retry = RetryPolicy::with_attempts(retries);
+	let progress = Progress::new(&broken);
+	let failures = rt.block_on(download_all(
+		&client,
+		broken.clone(),
+		&inst_dir,
+		parallel,
+		&retry,
+		&progress,
+	));
+	progress.finish();
+	for (e, err) in &failures {
+		eprintln!(
+			"Failed to download {}: {}",
+			e.name.clone().unwrap_or(e.destination.clone()),
+			err
+		);
+	}
+	extract_natives(&version_json, &inst_dir, &Environment::host())?;
+	lay_out_legacy_assets(&version_json, &inst_dir)?;
+
+	let remaining = broken
+		.iter()
+		.filter(|e| !entry_is_valid(e, &inst_dir))
+		.count();
+	if remaining > 0 {
+		return Err(format!("{} files could not be repaired", remaining).into());
+	}
+	println!("Repaired {} files.", broken.len());
 	Ok(())
 }
 
@@ -298,3 +924,525 @@ pub fn list_versions() -> Result<(), Box<dyn std::error::Error>> {
 	}
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::progress::ProgressMode;
+
+	fn linux(arch: &str) -> Environment {
+		Environment {
+			os_name: "linux".to_string(),
+			os_arch: arch.to_string(),
+			os_version: Some("6.8.0".to_string()),
+		}
+	}
+
+	fn fixture(name: &str) -> VersionJson {
+		let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+		VersionJson::parse(&fs::read_to_string(path).unwrap()).unwrap()
+	}
+
+	fn names(queue: &[DownloadEntry]) -> Vec<&str> {
+		queue.iter().map(|e| e.name.as_deref().unwrap()).collect()
+	}
+
+	#[test]
+	fn legacy_libraries_on_linux() {
+		let version = fixture("1.12.2.json");
+		let queue = library_downloads(&version.libraries, &linux("x86_64"));
+		assert_eq!(
+			names(&queue),
+			[
+				"com/mojang/patchy/1.3.9/patchy-1.3.9.jar",
+				"net/java/jinput/jinput/2.0.5/jinput-2.0.5.jar",
+				"net/java/jinput/jinput-platform/2.0.5/jinput-platform-2.0.5-natives-linux.jar",
+				"org/lwjgl/lwjgl/lwjgl/2.9.4-nightly-20150209/lwjgl-2.9.4-nightly-20150209.jar",
+				"org/lwjgl/lwjgl/lwjgl-platform/2.9.4-nightly-20150209/lwjgl-platform-2.9.4-nightly-20150209-natives-linux.jar",
+			]
+		);
+	}
+
+	#[test]
+	fn legacy_natives_substitute_arch() {
+		let version = fixture("1.12.2.json");
+		// Twitch's natives, and the rest of the queue, for Windows on `arch`.
+		let windows = |arch: &str| {
+			let env = Environment {
+				os_name: "windows".to_string(),
+				os_arch: arch.to_string(),
+				os_version: Some("10.0".to_string()),
+			};
+			let queue = library_downloads(&version.libraries, &env);
+			let (twitch, rest): (Vec<String>, Vec<String>) = names(&queue)
+				.into_iter()
+				.map(String::from)
+				.partition(|n| n.starts_with("tv/twitch/"));
+			(twitch, rest)
+		};
+
+		let (x86, x86_rest) = windows("x86");
+		let (x86_64, x86_64_rest) = windows("x86_64");
+		assert_eq!(
+			x86,
+			["tv/twitch/twitch-platform/6.5/twitch-platform-6.5-natives-windows-32.jar"]
+		);
+		assert_eq!(
+			x86_64,
+			["tv/twitch/twitch-platform/6.5/twitch-platform-6.5-natives-windows-64.jar"]
+		);
+		assert_eq!(windows("arm64").0, x86_64);
+		assert_eq!(x86_rest, x86_64_rest);
+	}
+
+	#[test]
+	fn modern_libraries_on_linux() {
+		let version = fixture("1.20.1.json");
+		let queue = library_downloads(&version.libraries, &linux("x86_64"));
+		assert_eq!(
+			names(&queue),
+			[
+				"com/mojang/logging/1.1.1/logging-1.1.1.jar",
+				"org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1.jar",
+				"org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-linux.jar",
+			]
+		);
+		assert!(queue.iter().all(|e| e.sha1.is_some() && e.size.is_some()));
+	}
+
+	#[test]
+	fn maven_coordinates_to_paths() {
+		assert_eq!(
+			maven_path("net.fabricmc:fabric-loader:0.15.11").as_deref(),
+			Some("net/fabricmc/fabric-loader/0.15.11/fabric-loader-0.15.11.jar")
+		);
+		assert_eq!(
+			maven_path("org.lwjgl:lwjgl:3.3.1:natives-linux").as_deref(),
+			Some("org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-linux.jar")
+		);
+		assert_eq!(
+			maven_path("de.oceanlabs.mcp:mcp_config:1.20.1@zip").as_deref(),
+			Some("de/oceanlabs/mcp/mcp_config/1.20.1/mcp_config-1.20.1.zip")
+		);
+		assert_eq!(maven_path("not-a-coordinate"), None);
+	}
+
+	#[test]
+	fn maven_libraries_from_loader_profile() {
+		let version = fixture("fabric-loader-0.15.11-1.20.1.json");
+		let queue = library_downloads(&version.libraries, &linux("x86_64"));
+		assert_eq!(
+			queue.iter().map(|e| e.url.as_str()).collect::<Vec<_>>(),
+			[
+				"https://maven.fabricmc.net/org/ow2/asm/asm/9.6/asm-9.6.jar",
+				"https://maven.fabricmc.net/net/fabricmc/intermediary/1.20.1/intermediary-1.20.1.jar",
+				"https://maven.fabricmc.net/net/fabricmc/fabric-loader/0.15.11/fabric-loader-0.15.11.jar",
+			]
+		);
+		assert_eq!(queue[0].size, Some(123598));
+		assert!(queue[1].sha1.is_none());
+	}
+
+	#[test]
+	fn arch_and_version_rules() {
+		let lib: Library = serde_json::from_str(
+			r#"{"name": "a:b:1", "rules": [{"action": "allow", "os": {"name": "linux", "arch": "aarch64"}}]}"#,
+		)
+		.unwrap();
+		assert!(!library_applies(&lib, &linux("x86_64")));
+		assert!(library_applies(&lib, &linux("arm64")));
+
+		let lib: Library = serde_json::from_str(
+			r#"{"name": "a:b:1", "rules": [{"action": "allow", "os": {"name": "linux", "version": "^6\\."}}]}"#,
+		)
+		.unwrap();
+		assert!(library_applies(&lib, &linux("x86_64")));
+		let mut old_kernel = linux("x86_64");
+		old_kernel.os_version = Some("5.15.0".to_string());
+		assert!(!library_applies(&lib, &old_kernel));
+		assert!(
+			serde_json::to_string(&lib)
+				.unwrap()
+				.contains(r#""version":"^6\\.""#)
+		);
+		assert!(
+			serde_json::from_str::<Library>(
+				r#"{"name": "a:b:1", "rules": [{"action": "allow", "os": {"version": "(("}}]}"#,
+			)
+			.is_err()
+		);
+	}
+
+	/// What the stand-in server does with one request.
+	#[derive(Clone, Copy)]
+	enum Reply {
+		/// Answer with this status and no body.
+		Status(u16),
+		/// Promise the full body but hang up after this many bytes.
+		Truncated(usize),
+		/// Serve the body, honouring `Range: bytes=N-`.
+		Body,
+	}
+
+	/// A tiny HTTP/1.1 server on localhost that answers requests according to
+	/// `script` (and with [`Reply::Body`] once the script runs out). Returns the
+	/// URL and the request heads it received.
+	async fn stand_in(
+		body: Vec<u8>,
+		script: Vec<Reply>,
+	) -> (String, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+		use tokio::io::AsyncReadExt;
+
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = format!("http://{}/file", listener.local_addr().unwrap());
+		let requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+		let seen = requests.clone();
+
+		tokio::spawn(async move {
+			let mut script = script.into_iter();
+			loop {
+				let Ok((mut sock, _)) = listener.accept().await else {
+					return;
+				};
+				let mut head = Vec::new();
+				let mut buf = [0u8; 1024];
+				while !head.ends_with(b"\r\n\r\n") {
+					let n = sock.read(&mut buf).await.unwrap();
+					if n == 0 {
+						break;
+					}
+					head.extend_from_slice(&buf[..n]);
+				}
+				let head = String::from_utf8_lossy(&head).to_lowercase();
+				seen.lock().unwrap().push(head.clone());
+
+				let start = head
+					.lines()
+					.find_map(|l| l.strip_prefix("range: bytes="))
+					.and_then(|r| r.trim_end_matches('-').parse::<usize>().ok());
+
+				let out = match script.next().unwrap_or(Reply::Body) {
+					Reply::Status(code) => format!(
+						"HTTP/1.1 {} X\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+						code
+					)
+					.into_bytes(),
+					Reply::Truncated(n) => {
+						let mut out = format!(
+							"HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+							body.len()
+						)
+						.into_bytes();
+						out.extend_from_slice(&body[..n]);
+						out
+					}
+					Reply::Body => {
+						let from = start.unwrap_or(0);
+						let status = if start.is_some() {
+							"206 Partial Content"
+						} else {
+							"200 OK"
+						};
+						let mut out = format!(
+							"HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+							status,
+							body.len() - from
+						)
+						.into_bytes();
+						out.extend_from_slice(&body[from..]);
+						out
+					}
+				};
+				_ = sock.write_all(&out).await;
+				_ = sock.shutdown().await;
+			}
+		});
+
+		(url, requests)
+	}
+
+	fn test_body() -> Vec<u8> {
+		(0..64 * 1024).map(|i| (i % 251) as u8).collect()
+	}
+
+	fn test_entry(url: &str, body: &[u8]) -> DownloadEntry {
+		DownloadEntry {
+			url: url.to_string(),
+			destination: "file.bin".to_string(),
+			size: Some(body.len()),
+			sha1: Some(format!("{:x}", Sha1::digest(body))),
+			name: Some("file.bin".to_string()),
+			shared: false,
+		}
+	}
+
+	fn test_dir() -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("vanta-test-{}", uuid::Uuid::new_v4()));
+		fs::create_dir_all(&dir).unwrap();
+		dir
+	}
+
+	fn fast_retry() -> RetryPolicy {
+		RetryPolicy {
+			attempts: 3,
+			base_delay: Duration::from_millis(1),
+			max_delay: Duration::from_millis(5),
+		}
+	}
+
+	fn hidden() -> Progress {
+		Progress::with_mode(&[], ProgressMode::Hidden)
+	}
+
+	#[tokio::test]
+	async fn retries_after_server_errors() {
+		let body = test_body();
+		let (url, requests) =
+			stand_in(body.clone(), vec![Reply::Status(500), Reply::Status(503)]).await;
+		let dir = test_dir();
+
+		download(
+			&Client::new(),
+			&test_entry(&url, &body),
+			&dir,
+			&fast_retry(),
+			&hidden(),
+		)
+		.await
+		.unwrap();
+
+		assert_eq!(fs::read(dir.join("file.bin")).unwrap(), body);
+		assert_eq!(requests.lock().unwrap().len(), 3);
+		assert!(!part_path(&dir.join("file.bin")).exists());
+		_ = fs::remove_dir_all(&dir);
+	}
+
+	#[tokio::test]
+	async fn resumes_partial_download() {
+		let body = test_body();
+		let (url, requests) = stand_in(body.clone(), vec![Reply::Truncated(20_000)]).await;
+		let dir = test_dir();
+
+		download(
+			&Client::new(),
+			&test_entry(&url, &body),
+			&dir,
+			&fast_retry(),
+			&hidden(),
+		)
+		.await
+		.unwrap();
+
+		assert_eq!(fs::read(dir.join("file.bin")).unwrap(), body);
+		let requests = requests.lock().unwrap();
+		assert_eq!(requests.len(), 2);
+		assert!(requests[1].contains("range: bytes=20000-"));
+		_ = fs::remove_dir_all(&dir);
+	}
+
+	#[tokio::test]
+	async fn rejects_corrupt_body() {
+		let body = test_body();
+		let (url, requests) = stand_in(body.clone(), vec![]).await;
+		let dir = test_dir();
+		let mut entry = test_entry(&url, &body);
+		entry.sha1 = Some("0".repeat(40));
+
+		let err = download(&Client::new(), &entry, &dir, &fast_retry(), &hidden())
+			.await
+			.unwrap_err();
+
+		assert!(err.to_string().contains("SHA-1 mismatch"));
+		assert_eq!(requests.lock().unwrap().len(), 3);
+		assert!(!dir.join("file.bin").exists());
+		assert!(!part_path(&dir.join("file.bin")).exists());
+		_ = fs::remove_dir_all(&dir);
+	}
+
+	#[tokio::test]
+	async fn reports_permanent_failures() {
+		let body = test_body();
+		let (good_url, _) = stand_in(body.clone(), vec![]).await;
+		let (bad_url, bad_requests) = stand_in(body.clone(), vec![Reply::Status(404)]).await;
+		let dir = test_dir();
+
+		let mut good = test_entry(&good_url, &body);
+		good.destination = "good.bin".to_string();
+		let mut bad = test_entry(&bad_url, &body);
+		bad.name = Some("missing.bin".to_string());
+
+		let failures = download_all(
+			&Client::new(),
+			vec![good, bad],
+			&dir,
+			2,
+			&fast_retry(),
+			&hidden(),
+		)
+		.await;
+
+		assert_eq!(failures.len(), 1);
+		assert_eq!(failures[0].0.name.as_deref(), Some("missing.bin"));
+		// 404s are not worth retrying.
+		assert_eq!(bad_requests.lock().unwrap().len(), 1);
+		assert_eq!(fs::read(dir.join("good.bin")).unwrap(), body);
+		assert!(report_failures(&failures).is_err());
+		_ = fs::remove_dir_all(&dir);
+	}
+
+	#[tokio::test]
+	async fn downloads_shared_destinations_once() {
+		let body = test_body();
+		let (url, requests) = stand_in(body.clone(), vec![]).await;
+		let dir = test_dir();
+
+		let first = test_entry(&url, &body);
+		let mut second = test_entry(&url, &body);
+		second.name = Some("other name, same hash".to_string());
+		let queue = dedup_queue(vec![first, second], &dir);
+		assert_eq!(queue.len(), 1);
+
+		let failures = download_all(&Client::new(), queue, &dir, 2, &fast_retry(), &hidden()).await;
+
+		assert!(failures.is_empty());
+		assert_eq!(requests.lock().unwrap().len(), 1);
+		assert_eq!(fs::read(dir.join("file.bin")).unwrap(), body);
+		_ = fs::remove_dir_all(&dir);
+	}
+
+	#[test]
+	fn extracts_natives() {
+		let version = VersionJson::parse(
+			r#"{
+				"id": "natives-test",
+				"libraries": [{
+					"name": "vanta.test:natives:1",
+					"downloads": {"classifiers": {
+						"natives-linux": {"path": "vanta/test/natives/1/natives-1-natives-linux.jar", "url": ""},
+						"natives-osx": {"path": "vanta/test/natives/1/natives-1-natives-osx.jar", "url": ""}
+					}},
+					"natives": {"linux": "natives-linux", "osx": "natives-osx"},
+					"extract": {"exclude": ["META-INF/"]}
+				}]
+			}"#,
+		)
+		.unwrap();
+		let jar = get_libraries_folder().join("vanta/test/natives/1/natives-1-natives-linux.jar");
+		fs::create_dir_all(jar.parent().unwrap()).unwrap();
+		let mut zip = zip::ZipWriter::new(File::create(&jar).unwrap());
+		for name in [
+			"liblwjgl.so",
+			"linux/x64/libglfw.so",
+			"META-INF/MANIFEST.MF",
+			"../escaped.so",
+		] {
+			zip.start_file(name, zip::write::SimpleFileOptions::default())
+				.unwrap();
+			io::Write::write_all(&mut zip, name.as_bytes()).unwrap();
+		}
+		zip.finish().unwrap();
+
+		let dir = test_dir();
+		let inst_dir = dir.join("instance");
+		extract_natives(&version, &inst_dir, &linux("x86_64")).unwrap();
+
+		let natives = inst_dir.join("versions/natives");
+		assert_eq!(
+			fs::read(natives.join("liblwjgl.so")).unwrap(),
+			b"liblwjgl.so"
+		);
+		assert!(natives.join("linux/x64/libglfw.so").is_file());
+		assert!(!natives.join("META-INF").exists());
+		assert!(!natives.join("../escaped.so").exists());
+		assert!(!inst_dir.join("versions/escaped.so").exists());
+		_ = fs::remove_dir_all(&dir);
+		_ = fs::remove_file(&jar);
+	}
+
+	/// Save an asset index with one object, `<id>.txt`, and store the object.
+	fn legacy_asset_index(id: &str, flags: &str) -> VersionJson {
+		let body = format!("asset of {}", id);
+		let hash = format!("{:x}", Sha1::digest(&body));
+		let object = get_assets_folder()
+			.join("objects")
+			.join(&hash[0..2])
+			.join(&hash);
+		fs::create_dir_all(object.parent().unwrap()).unwrap();
+		fs::write(&object, &body).unwrap();
+
+		let version = VersionJson::parse(&format!(
+			r#"{{"id": "{id}", "assetIndex": {{"id": "{id}", "url": ""}}}}"#
+		))
+		.unwrap();
+		let index = asset_index_path(&version);
+		fs::create_dir_all(index.parent().unwrap()).unwrap();
+		fs::write(
+			&index,
+			format!(
+				r#"{{{flags}"objects": {{"sounds/{id}.txt": {{"hash": "{hash}", "size": {}}}}}}}"#,
+				body.len()
+			),
+		)
+		.unwrap();
+		version
+	}
+
+	#[test]
+	fn lays_out_legacy_assets() {
+		let game_dir = test_dir();
+
+		let modern = legacy_asset_index("vanta-test-modern", "");
+		let assets = read_asset_index(&modern).unwrap();
+		assert_eq!(legacy_assets_dir(&modern, &assets, &game_dir), None);
+		lay_out_legacy_assets(&modern, &game_dir).unwrap();
+
+		let virtual_ = legacy_asset_index("vanta-test-virtual", r#""virtual": true, "#);
+		let assets = read_asset_index(&virtual_).unwrap();
+		let dir = get_assets_folder().join("virtual/vanta-test-virtual");
+		assert_eq!(
+			legacy_assets_dir(&virtual_, &assets, &game_dir).as_ref(),
+			Some(&dir)
+		);
+		lay_out_legacy_assets(&virtual_, &game_dir).unwrap();
+		assert_eq!(
+			fs::read_to_string(dir.join("sounds/vanta-test-virtual.txt")).unwrap(),
+			"asset of vanta-test-virtual"
+		);
+
+		let resources = legacy_asset_index("vanta-test-resources", r#""map_to_resources": true, "#);
+		let assets = read_asset_index(&resources).unwrap();
+		assert_eq!(
+			legacy_assets_dir(&resources, &assets, &game_dir),
+			Some(game_dir.join("resources"))
+		);
+		lay_out_legacy_assets(&resources, &game_dir).unwrap();
+		assert_eq!(
+			fs::read_to_string(game_dir.join("resources/sounds/vanta-test-resources.txt")).unwrap(),
+			"asset of vanta-test-resources"
+		);
+		// Only the layout the index asks for is made.
+		assert_eq!(fs::read_dir(&game_dir).unwrap().count(), 1);
+
+		_ = fs::remove_dir_all(&game_dir);
+		_ = fs::remove_dir_all(&dir);
+	}
+
+	#[test]
+	fn failed_install_leaves_nothing_behind() {
+		let id = "rollback-test";
+		let staging = helpers::get_instance_folder(&format!("{}{}", instance::STAGING_PREFIX, id));
+
+		let result = create_instance("1.20.1", id, |dir| {
+			assert_eq!(dir, staging);
+			fs::write(dir.join("half-written.jar"), "x")?;
+			Err("download failed".into())
+		});
+
+		assert_eq!(result.unwrap_err().to_string(), "download failed");
+		assert!(!staging.exists());
+		assert!(!helpers::get_instance_folder(id).exists());
+		// The lock went with the failed attempt, so the id can be tried again.
+		let (again, _lock) = instance::lock_staging(id).unwrap();
+		_ = fs::remove_dir_all(again);
+	}
+}
@@ -5,6 +5,7 @@ use std::{
 };
 
 use crate::authentication::User;
+use crate::config;
 
 pub const USER_FILE: &str = "accounts.json";
 
@@ -18,10 +19,9 @@ pub fn write_users(users: Vec<User>) -> Result<(), std::io::Error> {
 }
 
 pub fn write_bytes(filename: String, data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
-	match PathBuf::from(&filename).parent() {
-		Some(p) => fs::create_dir_all(p).expect("Failed to create directory."),
-		None => (),
-	};
+	if let Some(p) = PathBuf::from(&filename).parent() {
+		fs::create_dir_all(p).expect("Failed to create directory.");
+	}
 	let mut file = File::create(&filename)?;
 	file.write_all(data)?;
 	file.sync_all()?;
@@ -33,12 +33,7 @@ pub fn read_users() -> Vec<User> {
 	match File::open(get_data_folder(Some(USER_FILE))) {
 		Ok(file) => {
 			let reader = BufReader::new(file);
-			match serde_json::from_reader(reader) {
-				Ok(users) => users,
-				Err(_) => {
-					vec![]
-				}
-			}
+			serde_json::from_reader(reader).unwrap_or_default()
 		}
 		Err(_) => {
 			vec![]
@@ -56,9 +51,14 @@ pub fn upsert_user(vec: &mut Vec<User>, new_user: User) {
 }
 
 pub fn get_data_folder(append: Option<&str>) -> PathBuf {
-	let path = platform_dirs::AppDirs::new(Some("vanta"), true)
-		.unwrap()
-		.data_dir;
+	let path = match &config::get().data_dir {
+		Some(dir) => dir.clone(),
+		None => {
+			platform_dirs::AppDirs::new(Some("vanta"), true)
+				.unwrap()
+				.data_dir
+		}
+	};
 
 	if let Some(append_str) = append {
 		path.join(Path::new(append_str))
@@ -67,6 +67,49 @@ pub fn get_data_folder(append: Option<&str>) -> PathBuf {
 	}
 }
 
+/// The asset store shared by every instance.
+pub fn get_assets_folder() -> PathBuf {
+	get_data_folder(Some("assets"))
+}
+
+/// The library store shared by every instance.
+pub fn get_libraries_folder() -> PathBuf {
+	get_data_folder(Some("libraries"))
+}
+
 pub fn get_instance_folder(instance: &str) -> PathBuf {
 	get_data_folder(Some(&format!("instances/{}", instance)))
 }
+
+/// Print rows as a table with a header, padding each column to its widest cell.
+pub fn print_table(header: &[&str], rows: &[Vec<String>]) {
+	let mut widths: Vec<usize> = header.iter().map(|h| h.len()).collect();
+	for row in rows {
+		for (w, cell) in widths.iter_mut().zip(row) {
+			*w = (*w).max(cell.chars().count());
+		}
+	}
+	let line = |cells: Vec<&str>| {
+		cells
+			.iter()
+			.zip(&widths)
+			.map(|(c, w)| format!("{:<w$}", c, w = w))
+			.collect::<Vec<_>>()
+			.join(" | ")
+			.trim_end()
+			.to_string()
+	};
+
+	println!("{}", line(header.to_vec()));
+	println!(
+		"{}",
+		widths
+			.iter()
+			.map(|w| "-".repeat(*w))
+			.collect::<Vec<_>>()
+			.join("-|-")
+	);
+	for row in rows {
+		println!("{}", line(row.iter().map(String::as_str).collect()));
+	}
+}
@@ -0,0 +1,903 @@
+use std::{
+	collections::BTreeMap,
+	fs,
+	io::{self, Write},
+	path::{Path, PathBuf},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::helpers::{self, get_instance_folder};
+use crate::version::{self, VersionJson};
+
+/// Name of the metadata file kept in every instance folder.
+pub const INSTANCE_FILE: &str = "instance.json";
+
+/// Prefix of the hidden folders new instances are installed into before they get their id.
+pub const STAGING_PREFIX: &str = ".new-";
+
+/// Metadata of one instance, saved as `instance.json` in its folder. The field
+/// names match the ones the Python launcher wrote, so old files still load.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Instance {
+	/// Folder name; not stored in the file itself.
+	#[serde(skip)]
+	pub id: String,
+	pub name: String,
+	/// Minecraft version.
+	pub version: String,
+	/// Id of the version JSON that is launched, e.g. `fabric-loader-0.15.11-1.20.1`.
+	pub jar_version: String,
+	/// `vanilla`, `fabric`, `quilt`, `forge` or `neoforge`.
+	pub loader_name: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub loader_version: Option<String>,
+	pub group: String,
+	/// Unix timestamps in seconds.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub created: Option<u64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub last_played: Option<u64>,
+	/// Total time spent in game, in seconds.
+	pub playtime: u64,
+	/// Java to launch with instead of the Mojang runtime: a path, a release
+	/// like `17` or a minimum like `>=17`.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub java: Option<String>,
+	/// Initial heap size like `2G`, passed as `-Xms`.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub min_memory: Option<String>,
+	/// Maximum heap size like `8G`, passed as `-Xmx`.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub max_memory: Option<String>,
+	/// Name of one of the [`GC_PRESETS`].
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub gc_preset: Option<String>,
+	/// Extra JVM arguments, added after the version's own.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub jvm_args: Vec<String>,
+	/// Extra game arguments, added after the version's own.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub game_args: Vec<String>,
+	/// Files a loader installer's processors wrote into the shared libraries
+	/// folder, relative to it. No version JSON lists them, so `gc` keeps them by this.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub installer_outputs: Vec<String>,
+	/// Extra environment variables of the game process.
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub env: BTreeMap<String, String>,
+}
+
+/// Named sets of garbage collector flags an instance can pick with `--gc-preset`,
+/// with the first Java release that takes them. Shenandoah is also missing from
+/// Oracle's own builds, whatever their release.
+pub const GC_PRESETS: &[(&str, u32, &[&str])] = &[
+	(
+		// Aikar's G1 tuning, https://docs.papermc.io/paper/aikars-flags
+		"aikar",
+		8,
+		&[
+			"-XX:+UseG1GC",
+			"-XX:+ParallelRefProcEnabled",
+			"-XX:MaxGCPauseMillis=200",
+			"-XX:+UnlockExperimentalVMOptions",
+			"-XX:+DisableExplicitGC",
+			"-XX:+AlwaysPreTouch",
+			"-XX:G1NewSizePercent=30",
+			"-XX:G1MaxNewSizePercent=40",
+			"-XX:G1HeapRegionSize=8M",
+			"-XX:G1ReservePercent=20",
+			"-XX:G1HeapWastePercent=5",
+			"-XX:G1MixedGCCountTarget=4",
+			"-XX:InitiatingHeapOccupancyPercent=15",
+			"-XX:G1MixedGCLiveThresholdPercent=90",
+			"-XX:G1RSetUpdatingPauseTimePercent=5",
+			"-XX:SurvivorRatio=32",
+			"-XX:+PerfDisableSharedMem",
+			"-XX:MaxTenuringThreshold=1",
+		],
+	),
+	("g1", 8, &["-XX:+UseG1GC", "-XX:MaxGCPauseMillis=50"]),
+	// Experimental before Java 15.
+	("zgc", 15, &["-XX:+UseZGC"]),
+	("shenandoah", 12, &["-XX:+UseShenandoahGC"]),
+];
+
+/// The first Java release that takes a preset, and its flags.
+fn gc_preset(name: &str) -> Result<(u32, &'static [&'static str]), Box<dyn std::error::Error>> {
+	GC_PRESETS
+		.iter()
+		.find(|(n, _, _)| *n == name)
+		.map(|(_, java, flags)| (*java, *flags))
+		.ok_or_else(|| {
+			let names: Vec<&str> = GC_PRESETS.iter().map(|(n, _, _)| *n).collect();
+			format!(
+				"Unknown GC preset {}, choose one of {}",
+				name,
+				names.join(", ")
+			)
+			.into()
+		})
+}
+
+/// Bytes in a heap size as the JVM takes it: a number with a `K`, `M` or `G` suffix.
+pub fn memory_bytes(size: &str) -> Option<u64> {
+	let unit: u64 = match size.chars().last()?.to_ascii_uppercase() {
+		'K' => 1 << 10,
+		'M' => 1 << 20,
+		'G' => 1 << 30,
+		_ => return None,
+	};
+	let number: u64 = size[..size.len() - 1].parse().ok()?;
+	number.checked_mul(unit).filter(|_| number > 0)
+}
+
+impl Default for Instance {
+	fn default() -> Self {
+		Self {
+			id: String::new(),
+			name: String::new(),
+			version: String::new(),
+			jar_version: String::new(),
+			loader_name: "vanilla".to_string(),
+			loader_version: None,
+			group: "Ungrouped".to_string(),
+			created: None,
+			last_played: None,
+			playtime: 0,
+			java: None,
+			min_memory: None,
+			max_memory: None,
+			gc_preset: None,
+			jvm_args: vec![],
+			game_args: vec![],
+			installer_outputs: vec![],
+			env: BTreeMap::new(),
+		}
+	}
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |d| d.as_secs())
+}
+
+impl Instance {
+	/// Metadata for a fresh vanilla instance of `version`.
+	pub fn new(id: &str, version: &str) -> Self {
+		Self {
+			id: id.to_string(),
+			name: id.to_string(),
+			version: version.to_string(),
+			jar_version: version.to_string(),
+			created: Some(now()),
+			..Self::default()
+		}
+	}
+
+	pub fn dir(&self) -> PathBuf {
+		get_instance_folder(&self.id)
+	}
+
+	/// Load an instance's metadata. Instances created before metadata was kept
+	/// get it filled in from their version JSON.
+	pub fn load(id: &str) -> Result<Self, Box<dyn std::error::Error>> {
+		let dir = get_instance_folder(id);
+		if id.is_empty() || !dir.is_dir() {
+			return Err(format!("Instance {} does not exist", id).into());
+		}
+
+		let path = dir.join(INSTANCE_FILE);
+		let mut instance = if path.exists() {
+			let raw = fs::read_to_string(&path)
+				.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+			serde_json::from_str::<Instance>(&raw)
+				.map_err(|e| format!("Invalid {}: {}", path.display(), e))?
+		} else {
+			Self::from_version_files(id)?
+		};
+
+		instance.id = id.to_string();
+		if instance.name.is_empty() {
+			instance.name = id.to_string();
+		}
+		Ok(instance)
+	}
+
+	fn from_version_files(id: &str) -> Result<Self, Box<dyn std::error::Error>> {
+		let dir = get_instance_folder(id);
+		let client = VersionJson::read(&dir.join(version::CLIENT_JSON))
+			.map_err(|e| format!("Instance {} has no metadata: {}", id, e))?;
+		let mut instance = Self::new(id, &client.id);
+		instance.created = None;
+		if let Ok(loader) = VersionJson::read(&dir.join(version::LOADER_JSON)) {
+			instance.jar_version = loader.id;
+			instance.loader_name = "unknown".to_string();
+		}
+		Ok(instance)
+	}
+
+	pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+		self.save_in(&self.dir())
+	}
+
+	/// JVM arguments from the instance's settings layered with `config`, in the
+	/// order they are passed after the version's own: heap sizes, GC preset, then
+	/// its own arguments. `java_major` is the release the game runs on, if known,
+	/// to check the GC preset against.
+	pub fn launch_jvm_args(
+		&self,
+		config: &Config,
+		java_major: Option<u32>,
+	) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+		let mut args = vec![];
+		let (min, max) = config.memory_for(self)?;
+		if let Some(min) = min {
+			args.push(format!("-Xms{}", min));
+		}
+		if let Some(max) = max {
+			args.push(format!("-Xmx{}", max));
+		}
+		if let Some(name) = &self.gc_preset {
+			// Only a hand-edited instance.json gets here with an unknown name.
+			let (first_java, flags) =
+				gc_preset(name).map_err(|e| format!("Instance {}: {}", self.id, e))?;
+			if let Some(major) = java_major
+				&& major < first_java
+			{
+				return Err(format!(
+					"GC preset {} needs Java {} or newer, but {} runs on Java {}",
+					name, first_java, self.id, major
+				)
+				.into());
+			}
+			args.extend(flags.iter().map(|f| f.to_string()));
+		}
+		args.extend(self.jvm_args.iter().cloned());
+		Ok(args)
+	}
+
+	/// Apply launch setting changes, checking them first.
+	pub fn configure(&mut self, changes: &ConfigChanges) -> Result<(), Box<dyn std::error::Error>> {
+		let memory = |size: &Option<String>, current: &Option<String>| match size.as_deref() {
+			None => Ok(current.clone()),
+			Some("none") => Ok(None),
+			Some(size) => match memory_bytes(size) {
+				Some(_) => Ok(Some(size.to_uppercase())),
+				None => Err(format!(
+					"Invalid memory size {}, use a number with K, M or G like 4G",
+					size
+				)),
+			},
+		};
+		let min_memory = memory(&changes.min_memory, &self.min_memory)?;
+		let max_memory = memory(&changes.max_memory, &self.max_memory)?;
+		if let (Some(min), Some(max)) = (&min_memory, &max_memory)
+			&& memory_bytes(min) > memory_bytes(max)
+		{
+			return Err(format!("Minimum memory {} is above the maximum {}", min, max).into());
+		}
+		let gc_preset = match changes.gc_preset.as_deref() {
+			None => self.gc_preset.clone(),
+			Some("none") => None,
+			Some(name) => gc_preset(name).map(|_| Some(name.to_string()))?,
+		};
+		let mut env = vec![];
+		for pair in &changes.env {
+			match pair.split_once('=') {
+				Some((key, value)) if !key.is_empty() => {
+					env.push((key.to_string(), value.to_string()))
+				}
+				_ => {
+					return Err(
+						format!("Invalid environment variable {}, use KEY=VALUE", pair).into(),
+					);
+				}
+			}
+		}
+
+		self.min_memory = min_memory;
+		self.max_memory = max_memory;
+		self.gc_preset = gc_preset;
+		if changes.clear_jvm_args {
+			self.jvm_args.clear();
+		}
+		self.jvm_args.extend(changes.jvm_args.iter().cloned());
+		for key in &changes.unset_env {
+			self.env.remove(key);
+		}
+		self.env.extend(env);
+		Ok(())
+	}
+
+	/// Save the metadata into `dir` instead of the instance's own folder, e.g. while it is being staged.
+	pub fn save_in(&self, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+		helpers::write_bytes(
+			dir.join(INSTANCE_FILE).to_string_lossy().to_string(),
+			serde_json::to_string_pretty(self)?.as_bytes(),
+		)
+	}
+}
+
+/// Ids of every instance folder, sorted. Empty if there are no instances yet.
+/// Hidden folders (instances still being created) are left out.
+pub fn list_ids() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+	let dir = get_instance_folder("");
+	if !dir.exists() {
+		return Ok(vec![]);
+	}
+	let mut ids: Vec<String> = vec![];
+	for entry in fs::read_dir(&dir)? {
+		let entry = entry?;
+		let name = entry.file_name().to_string_lossy().to_string();
+		if entry.file_type()?.is_dir() && !name.starts_with('.') {
+			ids.push(name);
+		}
+	}
+	ids.sort();
+	Ok(ids)
+}
+
+/// Folders of instances that are still being installed.
+pub fn staging_folders() -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+	let dir = get_instance_folder("");
+	if !dir.exists() {
+		return Ok(vec![]);
+	}
+	let mut folders: Vec<PathBuf> = vec![];
+	for entry in fs::read_dir(&dir)? {
+		let entry = entry?;
+		let name = entry.file_name().to_string_lossy().to_string();
+		if entry.file_type()?.is_dir() && name.starts_with(STAGING_PREFIX) {
+			folders.push(entry.path());
+		}
+	}
+	folders.sort();
+	Ok(folders)
+}
+
+/// Create the staging folder of new instance `id`, and a lock that keeps other
+/// installs of the same id out until it is dropped. A folder already there is
+/// only cleared when nobody holds its lock, i.e. its install was killed. The
+/// lock file stays behind, so a waiting install never locks a replaced file.
+pub fn lock_staging(id: &str) -> Result<(PathBuf, fs::File), Box<dyn std::error::Error>> {
+	let staging = get_instance_folder(&format!("{}{}", STAGING_PREFIX, id));
+	fs::create_dir_all(get_instance_folder(""))?;
+	let path = get_instance_folder(&format!("{}{}.lock", STAGING_PREFIX, id));
+	let lock = fs::File::create(&path)
+		.map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+	match lock.try_lock() {
+		Ok(()) => (),
+		Err(fs::TryLockError::WouldBlock) => {
+			return Err(format!("Instance {} is already being created", id).into());
+		}
+		Err(fs::TryLockError::Error(e)) => {
+			return Err(format!("Failed to lock {}: {}", path.display(), e).into());
+		}
+	}
+	if staging.exists() {
+		fs::remove_dir_all(&staging)?;
+	}
+	fs::create_dir(&staging)?;
+	Ok((staging, lock))
+}
+
+/// Check that `id` can be used as a new instance folder name.
+pub fn validate_new_id(id: &str) -> Result<(), Box<dyn std::error::Error>> {
+	if id.is_empty() || id.starts_with('.') {
+		return Err(format!("Invalid instance id '{}'", id).into());
+	}
+	if let Some(c) = id.chars().find(|c| {
+		matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control()
+	}) {
+		return Err(format!(
+			"Instance id '{}' may not contain '{}'",
+			id,
+			c.escape_default()
+		)
+		.into());
+	}
+	if get_instance_folder(id).exists() {
+		return Err(format!("Instance {} already exists", id).into());
+	}
+	Ok(())
+}
+
+/// Create the empty folder of new instance `id`. Unlike [`validate_new_id`] this
+/// cannot race: of two commands taking the same id at once, one gets an error.
+fn claim_id(id: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+	let dir = get_instance_folder(id);
+	match fs::create_dir(&dir) {
+		Ok(()) => Ok(dir),
+		Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+			Err(format!("Instance {} already exists", id).into())
+		}
+		Err(e) => Err(format!("Failed to create {}: {}", dir.display(), e).into()),
+	}
+}
+
+/// Move the folder `from` into place as instance `id`, failing if the id was
+/// taken in the meantime.
+pub fn move_into_place(from: &Path, id: &str) -> Result<(), Box<dyn std::error::Error>> {
+	// Unix `rename` quietly replaces an empty folder, so claim the id first and
+	// replace our own claim. Windows refuses to rename onto any folder, leaving
+	// a short window between giving up the claim and the move.
+	let dir = claim_id(id)?;
+	if cfg!(windows) {
+		fs::remove_dir(&dir)?;
+	}
+	fs::rename(from, &dir).map_err(|e| {
+		_ = fs::remove_dir(&dir);
+		format!("Failed to move {} into place: {}", id, e).into()
+	})
+}
+
+/// Ask a yes/no question on stdin. Anything but `y`/`yes` is a no.
+fn confirm(question: &str) -> io::Result<bool> {
+	print!("{} [y/N] ", question);
+	io::stdout().flush()?;
+	let mut answer = String::new();
+	io::stdin().read_line(&mut answer)?;
+	Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Delete an instance with its worlds and settings, after asking unless `yes`.
+pub fn delete(id: &str, yes: bool) -> Result<(), Box<dyn std::error::Error>> {
+	let instance = Instance::load(id)?;
+	if !yes
+		&& !confirm(&format!(
+			"Delete instance {} ({}) including its worlds?",
+			instance.id, instance.name
+		))? {
+		println!("Aborted.");
+		return Ok(());
+	}
+	fs::remove_dir_all(instance.dir())
+		.map_err(|e| format!("Failed to delete {}: {}", instance.id, e))?;
+	println!("Deleted {}.", instance.id);
+	Ok(())
+}
+
+/// Give an instance a new id. Its display name follows along if it was the old id.
+pub fn rename(id: &str, new_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+	let mut instance = Instance::load(id)?;
+	validate_new_id(new_id)?;
+	move_into_place(&instance.dir(), new_id)?;
+
+	if instance.name == instance.id {
+		instance.name = new_id.to_string();
+	}
+	instance.id = new_id.to_string();
+	instance.save()?;
+	println!("Renamed {} to {}.", id, new_id);
+	Ok(())
+}
+
+/// Copy `src` into `dst` recursively. Files `link` accepts are hard-linked
+/// instead of copied where the filesystem allows it.
+fn copy_tree(src: &Path, dst: &Path, link: &dyn Fn(&Path) -> bool) -> io::Result<()> {
+	fs::create_dir_all(dst)?;
+	for entry in fs::read_dir(src)? {
+		let entry = entry?;
+		let (from, to) = (entry.path(), dst.join(entry.file_name()));
+		if entry.file_type()?.is_dir() {
+			copy_tree(&from, &to, link)?;
+		} else if !(link(&from) && fs::hard_link(&from, &to).is_ok()) {
+			fs::copy(&from, &to)?;
+		}
+	}
+	Ok(())
+}
+
+/// Whether a file under `versions/` can be shared between an instance and its
+/// clones: the client jar and natives never change after install, while the
+/// version JSONs are rewritten in place when a loader is installed.
+fn is_shared_game_file(path: &Path) -> bool {
+	path.extension().is_none_or(|e| e != "json")
+}
+
+/// Copy an instance: worlds, configs and mods are copied, while the game files
+/// under `versions/` are hard-linked (see [`is_shared_game_file`]).
+pub fn clone(id: &str, new_id: &str) -> Result<(), Box<dyn std::error::Error>> {
+	let source = Instance::load(id)?;
+	validate_new_id(new_id)?;
+	let dest = claim_id(new_id)?;
+
+	let copy = || -> io::Result<()> {
+		for entry in fs::read_dir(source.dir())? {
+			let entry = entry?;
+			let to = dest.join(entry.file_name());
+			if entry.file_type()?.is_dir() {
+				let link = if entry.file_name() == "versions" {
+					is_shared_game_file
+				} else {
+					|_: &Path| false
+				};
+				copy_tree(&entry.path(), &to, &link)?;
+			} else {
+				fs::copy(entry.path(), to)?;
+			}
+		}
+		Ok(())
+	};
+	if let Err(e) = copy() {
+		_ = fs::remove_dir_all(&dest);
+		return Err(format!("Failed to clone {}: {}", id, e).into());
+	}
+
+	let mut instance = source.clone();
+	instance.id = new_id.to_string();
+	if instance.name == source.id {
+		instance.name = new_id.to_string();
+	}
+	instance.created = Some(now());
+	instance.last_played = None;
+	instance.playtime = 0;
+	instance.save()?;
+	println!("Cloned {} to {}.", id, new_id);
+	Ok(())
+}
+
+/// Which instances `vanta instance list` shows.
+#[derive(Debug, Default)]
+pub struct ListFilter {
+	pub group: Option<String>,
+	pub loader: Option<String>,
+	pub version: Option<String>,
+}
+
+impl ListFilter {
+	fn matches(&self, instance: &Instance) -> bool {
+		let eq = |wanted: &Option<String>, value: &str| {
+			wanted
+				.as_ref()
+				.is_none_or(|w| w.eq_ignore_ascii_case(value))
+		};
+		eq(&self.group, &instance.group)
+			&& eq(&self.loader, &instance.loader_name)
+			&& eq(&self.version, &instance.version)
+	}
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ListEntry<'a> {
+	id: &'a str,
+	#[serde(flatten)]
+	instance: &'a Instance,
+	size_bytes: u64,
+}
+
+fn dir_size(dir: &Path) -> u64 {
+	let Ok(entries) = fs::read_dir(dir) else {
+		return 0;
+	};
+	entries
+		.flatten()
+		.map(|e| match e.file_type() {
+			Ok(t) if t.is_dir() => dir_size(&e.path()),
+			Ok(t) if t.is_file() => e.metadata().map_or(0, |m| m.len()),
+			_ => 0,
+		})
+		.sum()
+}
+
+/// A Unix timestamp as `YYYY-MM-DD HH:MM` in UTC.
+fn format_timestamp(secs: u64) -> String {
+	// Howard Hinnant's days-to-civil algorithm.
+	let days = (secs / 86400) as i64 + 719_468;
+	let era = days.div_euclid(146_097);
+	let doe = days.rem_euclid(146_097);
+	let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+	let time = secs % 86400;
+	format!(
+		"{:04}-{:02}-{:02} {:02}:{:02}",
+		year,
+		month,
+		day,
+		time / 3600,
+		time % 3600 / 60
+	)
+}
+
+fn format_size(bytes: u64) -> String {
+	format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// Changes to an instance's launch settings; what is left empty stays as it is.
+/// Sizes and the preset can be set to `none` to remove them.
+#[derive(Debug, Default)]
+pub struct ConfigChanges {
+	pub min_memory: Option<String>,
+	pub max_memory: Option<String>,
+	pub gc_preset: Option<String>,
+	/// Appended to the instance's JVM arguments.
+	pub jvm_args: Vec<String>,
+	/// Drop the existing JVM arguments before appending.
+	pub clear_jvm_args: bool,
+	/// `KEY=VALUE` pairs.
+	pub env: Vec<String>,
+	pub unset_env: Vec<String>,
+}
+
+impl ConfigChanges {
+	fn is_empty(&self) -> bool {
+		self.min_memory.is_none()
+			&& self.max_memory.is_none()
+			&& self.gc_preset.is_none()
+			&& self.jvm_args.is_empty()
+			&& !self.clear_jvm_args
+			&& self.env.is_empty()
+			&& self.unset_env.is_empty()
+	}
+}
+
+/// Change an instance's launch settings, or print them if there is nothing to change.
+pub fn config(id: &str, changes: &ConfigChanges) -> Result<(), Box<dyn std::error::Error>> {
+	let mut instance = Instance::load(id)?;
+	if !changes.is_empty() {
+		instance.configure(changes)?;
+		instance.save()?;
+		println!("Updated launch settings of {}.", instance.id);
+	}
+
+	let or_default = |v: &Option<String>| v.clone().unwrap_or_else(|| "default".to_string());
+	println!("Java:       {}", instance.java.as_deref().unwrap_or("auto"));
+	println!("Min memory: {}", or_default(&instance.min_memory));
+	println!("Max memory: {}", or_default(&instance.max_memory));
+	println!(
+		"GC preset:  {}",
+		instance.gc_preset.as_deref().unwrap_or("none")
+	);
+	println!("JVM args:   {}", instance.jvm_args.join(" "));
+	println!("Game args:  {}", instance.game_args.join(" "));
+	for (key, value) in &instance.env {
+		println!("Env:        {}={}", key, value);
+	}
+	Ok(())
+}
+
+/// Print the instances matching `filter` as a table, or as JSON for scripts.
+pub fn list(filter: &ListFilter, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+	let mut instances: Vec<(Instance, u64)> = vec![];
+	for id in list_ids()? {
+		match Instance::load(&id) {
+			Ok(i) if filter.matches(&i) => {
+				let size = dir_size(&i.dir());
+				instances.push((i, size));
+			}
+			Ok(_) => (),
+			Err(e) => eprintln!("WARN: Skipping {}: {}", id, e),
+		}
+	}
+
+	if json {
+		let entries: Vec<ListEntry> = instances
+			.iter()
+			.map(|(i, size)| ListEntry {
+				id: &i.id,
+				instance: i,
+				size_bytes: *size,
+			})
+			.collect();
+		println!("{}", serde_json::to_string_pretty(&entries)?);
+		return Ok(());
+	}
+
+	let header = [
+		"ID",
+		"Name",
+		"Version",
+		"Loader",
+		"Group",
+		"Last played",
+		"Size",
+	];
+	let rows: Vec<Vec<String>> = instances
+		.iter()
+		.map(|(i, size)| {
+			let loader = match &i.loader_version {
+				Some(v) => format!("{} {}", i.loader_name, v),
+				None => i.loader_name.clone(),
+			};
+			vec![
+				i.id.clone(),
+				i.name.clone(),
+				i.version.clone(),
+				loader,
+				i.group.clone(),
+				i.last_played.map_or("never".to_string(), format_timestamp),
+				format_size(*size),
+			]
+		})
+		.collect();
+	helpers::print_table(&header, &rows);
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reads_python_metadata() {
+		let instance: Instance = serde_json::from_str(
+			r#"{"name": "Skyblock", "version": "1.20.1", "jarVersion": "fabric-loader-0.15.11-1.20.1", "loaderName": "fabric", "group": "Modded"}"#,
+		)
+		.unwrap();
+		assert_eq!(instance.name, "Skyblock");
+		assert_eq!(instance.loader_name, "fabric");
+		assert_eq!(instance.group, "Modded");
+		assert_eq!(instance.playtime, 0);
+		assert!(instance.java.is_none());
+	}
+
+	#[test]
+	fn formats_timestamps() {
+		assert_eq!(format_timestamp(0), "1970-01-01 00:00");
+		assert_eq!(format_timestamp(951_827_696), "2000-02-29 12:34");
+		assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13");
+	}
+
+	#[test]
+	fn filters_instances() {
+		let mut instance = Instance::new("test", "1.20.1");
+		instance.loader_name = "fabric".to_string();
+		instance.group = "Modded".to_string();
+
+		assert!(ListFilter::default().matches(&instance));
+		let filter = ListFilter {
+			group: Some("modded".to_string()),
+			loader: Some("fabric".to_string()),
+			version: None,
+		};
+		assert!(filter.matches(&instance));
+		let filter = ListFilter {
+			version: Some("1.12.2".to_string()),
+			..ListFilter::default()
+		};
+		assert!(!filter.matches(&instance));
+	}
+
+	#[test]
+	fn rejects_bad_ids() {
+		for id in ["", ".hidden", "a/b", "a\\b", "what?"] {
+			assert!(validate_new_id(id).is_err(), "{:?}", id);
+		}
+	}
+
+	#[test]
+	fn copy_tree_links_files() {
+		let root = std::env::temp_dir().join(format!("vanta-copy-{}", std::process::id()));
+		let src = root.join("src");
+		fs::create_dir_all(src.join("natives")).unwrap();
+		fs::write(src.join("client.jar"), b"jar").unwrap();
+		fs::write(src.join("client.json"), b"vanilla").unwrap();
+		fs::write(src.join("natives/lib.so"), b"so").unwrap();
+
+		copy_tree(&src, &root.join("linked"), &is_shared_game_file).unwrap();
+		copy_tree(&src, &root.join("copied"), &|_| false).unwrap();
+		assert_eq!(fs::read(root.join("linked/natives/lib.so")).unwrap(), b"so");
+
+		// Installing a loader into a clone rewrites its version JSON in place,
+		// which must not reach the source.
+		let json = root.join("linked/client.json");
+		helpers::write_bytes(json.to_string_lossy().to_string(), b"quilt").unwrap();
+		assert_eq!(fs::read(src.join("client.json")).unwrap(), b"vanilla");
+		fs::write(root.join("copied/client.jar"), b"changed").unwrap();
+		assert_eq!(fs::read(src.join("client.jar")).unwrap(), b"jar");
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::MetadataExt;
+			let ino = |p: PathBuf| fs::metadata(p).unwrap().ino();
+			assert_eq!(
+				ino(src.join("client.jar")),
+				ino(root.join("linked/client.jar"))
+			);
+		}
+		_ = fs::remove_dir_all(&root);
+	}
+
+	#[test]
+	fn round_trips() {
+		let mut instance = Instance::new("test", "1.20.1");
+		instance.jvm_args = vec!["-Xmx4G".to_string()];
+		instance.last_played = Some(1_700_000_000);
+		instance.installer_outputs = vec![
+			"net/minecraft/client/1.20.1-20230612.114412/client-1.20.1-20230612.114412-srg.jar"
+				.to_string(),
+		];
+		let raw = serde_json::to_string(&instance).unwrap();
+		assert!(raw.contains("\"jvmArgs\""));
+		assert!(raw.contains("\"installerOutputs\""));
+		assert!(!raw.contains("\"id\""));
+
+		let loaded: Instance = serde_json::from_str(&raw).unwrap();
+		assert_eq!(loaded.jvm_args, instance.jvm_args);
+		assert_eq!(loaded.installer_outputs, instance.installer_outputs);
+		assert_eq!(loaded.last_played, instance.last_played);
+		assert_eq!(loaded.loader_name, "vanilla");
+	}
+
+	#[test]
+	fn configures_launch_settings() {
+		let mut instance = Instance::new("test", "1.20.1");
+		instance.jvm_args = vec!["-Dold=1".to_string()];
+		instance
+			.configure(&ConfigChanges {
+				min_memory: Some("2g".to_string()),
+				max_memory: Some("8G".to_string()),
+				gc_preset: Some("zgc".to_string()),
+				jvm_args: vec!["-Dfoo=bar".to_string()],
+				env: vec!["MESA_GL_VERSION_OVERRIDE=4.5".to_string()],
+				..ConfigChanges::default()
+			})
+			.unwrap();
+		assert_eq!(
+			instance.launch_jvm_args(&Config::default(), None).unwrap(),
+			["-Xms2G", "-Xmx8G", "-XX:+UseZGC", "-Dold=1", "-Dfoo=bar"]
+		);
+		assert_eq!(instance.env["MESA_GL_VERSION_OVERRIDE"], "4.5");
+
+		instance
+			.configure(&ConfigChanges {
+				min_memory: Some("none".to_string()),
+				gc_preset: Some("none".to_string()),
+				clear_jvm_args: true,
+				unset_env: vec!["MESA_GL_VERSION_OVERRIDE".to_string()],
+				..ConfigChanges::default()
+			})
+			.unwrap();
+		assert_eq!(
+			instance.launch_jvm_args(&Config::default(), None).unwrap(),
+			["-Xmx8G"]
+		);
+		assert!(instance.env.is_empty());
+		instance.gc_preset = Some("cms".to_string());
+		assert!(instance.launch_jvm_args(&Config::default(), None).is_err());
+		instance.gc_preset = Some("zgc".to_string());
+		assert!(
+			instance
+				.launch_jvm_args(&Config::default(), Some(11))
+				.is_err()
+		);
+		assert!(
+			instance
+				.launch_jvm_args(&Config::default(), Some(17))
+				.is_ok()
+		);
+		instance.gc_preset = None;
+
+		let invalid = |changes: ConfigChanges| instance.clone().configure(&changes).is_err();
+		assert!(invalid(ConfigChanges {
+			max_memory: Some("8".to_string()),
+			..ConfigChanges::default()
+		}));
+		assert!(invalid(ConfigChanges {
+			max_memory: Some("99999999999999G".to_string()),
+			..ConfigChanges::default()
+		}));
+		assert!(invalid(ConfigChanges {
+			min_memory: Some("16G".to_string()),
+			..ConfigChanges::default()
+		}));
+		assert!(invalid(ConfigChanges {
+			gc_preset: Some("cms".to_string()),
+			..ConfigChanges::default()
+		}));
+		assert!(invalid(ConfigChanges {
+			env: vec!["NOVALUE".to_string()],
+			..ConfigChanges::default()
+		}));
+	}
+}
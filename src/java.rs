@@ -0,0 +1,422 @@
+use std::{
+	collections::HashMap,
+	fmt, fs,
+	path::{Path, PathBuf},
+	process::Command,
+	time::UNIX_EPOCH,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::{self, get_data_folder};
+use crate::instance::Instance;
+use crate::rules::{Environment, normalize_arch};
+use crate::runtime;
+
+/// Where what was learned about each Java installation is cached.
+const CACHE_FILE: &str = "java.json";
+
+/// A Java installation found on this machine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JavaInstall {
+	/// The `java` executable.
+	pub path: PathBuf,
+	/// Full version, e.g. `17.0.15` or `1.8.0_392`.
+	pub version: String,
+	/// Feature release, e.g. `17` or `8`.
+	pub major: u32,
+	pub vendor: String,
+	/// Normalised like the rules' architectures: `x86_64`, `arm64`, ...
+	pub arch: String,
+	/// Where it was found: `JAVA_HOME`, `system`, `sdkman`, `asdf` or `mojang`.
+	pub source: String,
+	/// Modification time of the executable when it was probed, to notice upgrades.
+	modified: u64,
+}
+
+/// The Java an instance asks for, as stored in its metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JavaSpec {
+	/// A `java` executable or a Java home.
+	Path(PathBuf),
+	/// Any Java of at least this feature release, written `>=17` or `17+`.
+	AtLeast(u32),
+	/// A Java of exactly this feature release, written `17`.
+	Exactly(u32),
+}
+
+impl JavaSpec {
+	pub fn parse(spec: &str) -> Self {
+		let spec = spec.trim();
+		let at_least = spec
+			.strip_prefix(">=")
+			.or_else(|| spec.strip_suffix('+'))
+			.and_then(|n| n.trim().parse().ok());
+		if let Some(major) = at_least {
+			return JavaSpec::AtLeast(major);
+		}
+		match spec.parse() {
+			Ok(major) => JavaSpec::Exactly(major),
+			Err(_) => JavaSpec::Path(PathBuf::from(spec)),
+		}
+	}
+
+	fn matches(&self, install: &JavaInstall) -> bool {
+		match self {
+			JavaSpec::Path(p) => *p == install.path,
+			JavaSpec::AtLeast(major) => install.major >= *major,
+			JavaSpec::Exactly(major) => install.major == *major,
+		}
+	}
+}
+
+impl fmt::Display for JavaSpec {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			JavaSpec::Path(p) => write!(f, "{}", p.display()),
+			JavaSpec::AtLeast(major) => write!(f, ">={}", major),
+			JavaSpec::Exactly(major) => write!(f, "{}", major),
+		}
+	}
+}
+
+/// The `java` executable of a Java home.
+pub fn executable(home: &Path) -> PathBuf {
+	if cfg!(windows) {
+		home.join("bin/java.exe")
+	} else {
+		home.join("bin/java")
+	}
+}
+
+fn home_folder() -> Option<PathBuf> {
+	std::env::var_os("HOME")
+		.or_else(|| std::env::var_os("USERPROFILE"))
+		.map(PathBuf::from)
+}
+
+/// Every folder directly inside `dir`, sorted.
+fn subfolders(dir: &Path) -> Vec<PathBuf> {
+	let Ok(entries) = fs::read_dir(dir) else {
+		return vec![];
+	};
+	let mut dirs: Vec<PathBuf> = entries
+		.filter_map(|e| e.ok())
+		.map(|e| e.path())
+		.filter(|p| p.is_dir())
+		.collect();
+	dirs.sort();
+	dirs
+}
+
+/// Java homes worth looking at, with where they were found.
+fn candidates() -> Vec<(PathBuf, &'static str)> {
+	let mut homes = vec![];
+	if let Some(home) = std::env::var_os("JAVA_HOME") {
+		homes.push((PathBuf::from(home), "JAVA_HOME"));
+	}
+	for dir in subfolders(Path::new("/usr/lib/jvm")) {
+		homes.push((dir, "system"));
+	}
+	for dir in subfolders(Path::new("/Library/Java/JavaVirtualMachines")) {
+		homes.push((dir.join("Contents/Home"), "system"));
+	}
+
+	let user_dir = |var: &str, default: &str| {
+		std::env::var_os(var)
+			.map(PathBuf::from)
+			.or_else(|| home_folder().map(|h| h.join(default)))
+	};
+	if let Some(sdkman) = user_dir("SDKMAN_DIR", ".sdkman") {
+		for dir in subfolders(&sdkman.join("candidates/java")) {
+			homes.push((dir, "sdkman"));
+		}
+	}
+	if let Some(asdf) = user_dir("ASDF_DATA_DIR", ".asdf") {
+		for dir in subfolders(&asdf.join("installs/java")) {
+			homes.push((dir, "asdf"));
+		}
+	}
+
+	for home in runtime::homes() {
+		homes.push((home, "mojang"));
+	}
+	homes
+}
+
+/// Parse a `release` file as found in Java homes: `KEY="value"` lines.
+fn parse_release(text: &str) -> HashMap<String, String> {
+	text.lines()
+		.filter_map(|l| l.split_once('='))
+		.map(|(k, v)| (k.trim().to_string(), v.trim().trim_matches('"').to_string()))
+		.collect()
+}
+
+/// Parse the output of `java -XshowSettings:properties -version`: `key = value` lines.
+fn parse_properties(text: &str) -> HashMap<String, String> {
+	text.lines()
+		.filter_map(|l| l.split_once(" = "))
+		.map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+		.collect()
+}
+
+/// The feature release of a Java version: 8 for `1.8.0_392`, 17 for `17.0.15`.
+fn major_version(version: &str) -> Option<u32> {
+	let mut numbers = version
+		.split(|c: char| !c.is_ascii_digit())
+		.filter(|n| !n.is_empty());
+	match numbers.next()?.parse().ok()? {
+		1 => numbers.next()?.parse().ok(),
+		major => Some(major),
+	}
+}
+
+/// Find out version, vendor and architecture of the Java in `home`, from its
+/// `release` file if it has one and by asking `java` itself otherwise.
+fn probe(java: &Path, home: &Path) -> Result<(String, String, String), Box<dyn std::error::Error>> {
+	let release = fs::read_to_string(home.join("release"))
+		.map(|t| parse_release(&t))
+		.unwrap_or_default();
+	let (version, vendor, arch) = match release.get("JAVA_VERSION") {
+		Some(version) => (
+			version.clone(),
+			release.get("IMPLEMENTOR").cloned(),
+			release.get("OS_ARCH").cloned(),
+		),
+		None => {
+			let output = Command::new(java)
+				.args(["-XshowSettings:properties", "-version"])
+				.output()
+				.map_err(|e| format!("Failed to run {}: {}", java.display(), e))?;
+			// The settings are printed to stderr.
+			let properties = parse_properties(&String::from_utf8_lossy(&output.stderr));
+			let version = properties
+				.get("java.version")
+				.ok_or(format!("{} did not report its version", java.display()))?;
+			(
+				version.clone(),
+				properties.get("java.vendor").cloned(),
+				properties.get("os.arch").cloned(),
+			)
+		}
+	};
+	Ok((
+		version,
+		vendor.unwrap_or_else(|| "unknown".to_string()),
+		normalize_arch(&arch.unwrap_or_else(|| "unknown".to_string())).to_string(),
+	))
+}
+
+/// The feature release of the `java` executable at `java`, if it can be found out.
+pub fn major_of(java: &Path) -> Option<u32> {
+	let home = java.parent()?.parent()?;
+	let (version, _, _) = probe(java, home).ok()?;
+	major_version(&version)
+}
+
+fn read_cache() -> Vec<JavaInstall> {
+	fs::read_to_string(get_data_folder(Some(CACHE_FILE)))
+		.ok()
+		.and_then(|t| serde_json::from_str(&t).ok())
+		.unwrap_or_default()
+}
+
+/// Find every Java installation, probing only those that changed since they
+/// were cached unless `refresh`.
+pub fn discover(refresh: bool) -> Result<Vec<JavaInstall>, Box<dyn std::error::Error>> {
+	let cache = if refresh { vec![] } else { read_cache() };
+	let mut installs: Vec<JavaInstall> = vec![];
+	for (home, source) in candidates() {
+		let java = executable(&home);
+		// Homes are often reachable through several links; list each once.
+		let Ok(path) = java.canonicalize() else {
+			continue;
+		};
+		if installs.iter().any(|i| i.path == path) {
+			continue;
+		}
+		let modified = fs::metadata(&path)
+			.and_then(|m| m.modified())
+			.map_or(0, |t| {
+				t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+			});
+		if let Some(cached) = cache
+			.iter()
+			.find(|i| i.path == path && i.modified == modified)
+		{
+			installs.push(JavaInstall {
+				source: source.to_string(),
+				..cached.clone()
+			});
+			continue;
+		}
+
+		match probe(&path, &home) {
+			Ok((version, vendor, arch)) => installs.push(JavaInstall {
+				major: major_version(&version).unwrap_or(0),
+				path,
+				version,
+				vendor,
+				arch,
+				source: source.to_string(),
+				modified,
+			}),
+			Err(e) => eprintln!("WARN: Skipping {}: {}", home.display(), e),
+		}
+	}
+
+	helpers::write_bytes(
+		get_data_folder(Some(CACHE_FILE))
+			.to_string_lossy()
+			.to_string(),
+		serde_json::to_string_pretty(&installs)?.as_bytes(),
+	)?;
+	Ok(installs)
+}
+
+/// The installation that best fits `spec` on a machine of `arch`: the oldest
+/// release that satisfies it, as Minecraft and its mod loaders are most at home
+/// on the Java they target. Other architectures' Javas cannot load the natives.
+fn choose<'a>(spec: &JavaSpec, installs: &'a [JavaInstall], arch: &str) -> Option<&'a JavaInstall> {
+	installs
+		.iter()
+		.filter(|i| i.arch == arch && spec.matches(i))
+		.min_by_key(|i| i.major)
+}
+
+/// The `java` executable to run for `spec`.
+pub fn resolve(spec: &JavaSpec) -> Result<String, Box<dyn std::error::Error>> {
+	if let JavaSpec::Path(path) = spec {
+		let java = if path.is_dir() {
+			executable(path)
+		} else {
+			path.clone()
+		};
+		return Ok(java.to_string_lossy().to_string());
+	}
+	let installs = discover(false)?;
+	let install = choose(spec, &installs, &Environment::host().os_arch)
+		.ok_or(format!("No Java {} found, see `vanta java list`", spec))?;
+	Ok(install.path.to_string_lossy().to_string())
+}
+
+/// Print every Java installation found.
+pub fn list(json: bool, refresh: bool) -> Result<(), Box<dyn std::error::Error>> {
+	let installs = discover(refresh)?;
+	if json {
+		println!("{}", serde_json::to_string_pretty(&installs)?);
+		return Ok(());
+	}
+
+	let rows: Vec<Vec<String>> = installs
+		.iter()
+		.map(|i| {
+			vec![
+				i.version.clone(),
+				i.vendor.clone(),
+				i.arch.clone(),
+				i.source.clone(),
+				i.path.to_string_lossy().to_string(),
+			]
+		})
+		.collect();
+	helpers::print_table(&["Version", "Vendor", "Arch", "Source", "Path"], &rows);
+	Ok(())
+}
+
+/// Pin the Java an instance runs with. `auto` goes back to the Mojang runtime
+/// its version asks for.
+pub fn pin(instance: &str, java: &str) -> Result<(), Box<dyn std::error::Error>> {
+	let mut instance = Instance::load(instance)?;
+	if java == "auto" {
+		instance.java = None;
+		instance.save()?;
+		println!("{} now uses the Java its version asks for.", instance.id);
+		return Ok(());
+	}
+
+	let spec = JavaSpec::parse(java);
+	match &spec {
+		JavaSpec::Path(path) if path.components().count() > 1 && !path.exists() => {
+			return Err(format!("No Java at {}", path.display()).into());
+		}
+		JavaSpec::Path(_) => (),
+		_ => {
+			let installs = discover(false)?;
+			if choose(&spec, &installs, &Environment::host().os_arch).is_none() {
+				return Err(format!("No Java {} found, see `vanta java list`", spec).into());
+			}
+		}
+	}
+	instance.java = Some(spec.to_string());
+	instance.save()?;
+	println!("{} now uses Java {}.", instance.id, spec);
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn install(path: &str, major: u32) -> JavaInstall {
+		JavaInstall {
+			path: PathBuf::from(path),
+			version: major.to_string(),
+			major,
+			vendor: "Test".to_string(),
+			arch: "x86_64".to_string(),
+			source: "system".to_string(),
+			modified: 0,
+		}
+	}
+
+	#[test]
+	fn parses_specs() {
+		assert_eq!(JavaSpec::parse(">=17"), JavaSpec::AtLeast(17));
+		assert_eq!(JavaSpec::parse("21+"), JavaSpec::AtLeast(21));
+		assert_eq!(JavaSpec::parse(" 8 "), JavaSpec::Exactly(8));
+		assert_eq!(
+			JavaSpec::parse("/usr/lib/jvm/java-17/bin/java"),
+			JavaSpec::Path(PathBuf::from("/usr/lib/jvm/java-17/bin/java"))
+		);
+		for spec in [">=17", "8", "/opt/java/bin/java"] {
+			assert_eq!(JavaSpec::parse(spec).to_string(), spec);
+		}
+	}
+
+	#[test]
+	fn reads_versions() {
+		assert_eq!(major_version("1.8.0_392"), Some(8));
+		assert_eq!(major_version("17.0.15"), Some(17));
+		assert_eq!(major_version("21"), Some(21));
+		assert_eq!(major_version("22-ea"), Some(22));
+		assert_eq!(major_version(""), None);
+
+		let release =
+			parse_release("IMPLEMENTOR=\"Debian\"\nJAVA_VERSION=\"17.0.15\"\nOS_ARCH=\"x86_64\"\n");
+		assert_eq!(release["JAVA_VERSION"], "17.0.15");
+		assert_eq!(release["IMPLEMENTOR"], "Debian");
+
+		let properties = parse_properties(
+			"Property settings:\n    java.vendor = Eclipse Adoptium\n    java.version = 1.8.0_392\n    os.arch = amd64\n\nopenjdk version \"1.8.0_392\"\n",
+		);
+		assert_eq!(properties["java.version"], "1.8.0_392");
+		assert_eq!(properties["java.vendor"], "Eclipse Adoptium");
+		assert_eq!(normalize_arch(&properties["os.arch"]), "x86_64");
+	}
+
+	#[test]
+	fn chooses_oldest_matching_java() {
+		let mut arm = install("/d", 11);
+		arm.arch = "arm64".to_string();
+		let installs = [install("/a", 21), install("/b", 17), install("/c", 8), arm];
+		let chosen =
+			|spec: &str| choose(&JavaSpec::parse(spec), &installs, "x86_64").map(|i| i.major);
+		assert_eq!(chosen(">=17"), Some(17));
+		assert_eq!(chosen(">=18"), Some(21));
+		assert_eq!(chosen("8"), Some(8));
+		assert_eq!(chosen("11"), None);
+		assert_eq!(chosen("/a"), Some(21));
+	}
+}
@@ -0,0 +1,367 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::download;
+use crate::helpers::{get_assets_folder, get_libraries_folder};
+use crate::rules::{Environment, Features, rules_allow};
+use crate::version::{Argument, ArgumentValue, VersionJson};
+
+use super::lc_types::Options;
+
+/// Separator the JVM expects between classpath entries on this platform.
+#[cfg(windows)]
+pub const CLASSPATH_SEPARATOR: &str = ";";
+#[cfg(not(windows))]
+pub const CLASSPATH_SEPARATOR: &str = ":";
+
+/// Build the classpath from the version's libraries plus the client jar.
+pub fn build_classpath(version: &VersionJson, inst_dir: &Path, env: &Environment) -> String {
+	let mut entries: Vec<String> = vec![];
+
+	for l in version
+		.libraries
+		.iter()
+		.filter(|l| download::library_applies(l, env))
+	{
+		if let Some(path) = download::library_artifact(l).and_then(|a| a.path) {
+			let lib = get_libraries_folder().join(path);
+			entries.push(lib.to_string_lossy().to_string());
+		}
+	}
+
+	entries.push(
+		inst_dir
+			.join("versions/client.jar")
+			.to_string_lossy()
+			.to_string(),
+	);
+
+	entries.join(CLASSPATH_SEPARATOR)
+}
+
+/// Values for the `${...}` placeholders used in version arguments.
+pub fn launch_variables(
+	options: &Options,
+	version: &VersionJson,
+	classpath: &str,
+) -> HashMap<&'static str, String> {
+	let version_name = version.id.as_str();
+	let version_type = version.r#type.as_deref().unwrap_or("release");
+	let assets_index = download::asset_index_id(version);
+	let assets_root = get_assets_folder().to_string_lossy().to_string();
+	let game_assets = download::read_asset_index(version)
+		.ok()
+		.and_then(|a| download::legacy_assets_dir(version, &a, Path::new(&options.game_directory)))
+		.map_or(assets_root.clone(), |d| d.to_string_lossy().to_string());
+	let quick_play = |v: &Option<serde_json::Value>| {
+		v.as_ref()
+			.and_then(|v| v.as_str())
+			.unwrap_or_default()
+			.to_string()
+	};
+
+	HashMap::from([
+		("auth_player_name", options.username.clone()),
+		("auth_uuid", options.uuid.clone()),
+		("auth_access_token", options.token.clone()),
+		(
+			"auth_session",
+			format!("token:{}:{}", options.token, options.uuid),
+		),
+		("auth_xuid", "0".to_string()),
+		("clientid", crate::config::get().auth.client_id.clone()),
+		("user_type", "msa".to_string()),
+		("user_properties", "{}".to_string()),
+		("version_name", version_name.to_string()),
+		("version_type", version_type.to_string()),
+		("game_directory", options.game_directory.clone()),
+		("assets_root", assets_root.clone()),
+		("game_assets", game_assets),
+		("assets_index_name", assets_index.to_string()),
+		("natives_directory", options.natives_directory.clone()),
+		(
+			"library_directory",
+			get_libraries_folder().to_string_lossy().to_string(),
+		),
+		("classpath", classpath.to_string()),
+		("classpath_separator", CLASSPATH_SEPARATOR.to_string()),
+		("launcher_name", options.launcher_name.clone()),
+		("launcher_version", options.launcher_version.clone()),
+		("resolution_width", options.resolution_width.clone()),
+		("resolution_height", options.resolution_height.clone()),
+		("quickPlayPath", quick_play(&options.quick_play_path)),
+		(
+			"quickPlaySingleplayer",
+			quick_play(&options.quick_play_singleplayer),
+		),
+		(
+			"quickPlayMultiplayer",
+			quick_play(&options.quick_play_multiplayer),
+		),
+		("quickPlayRealms", quick_play(&options.quick_play_realms)),
+	])
+}
+
+/// Replace every `${name}` in `arg` with its value. Unknown placeholders are left as-is.
+pub fn substitute(arg: &str, vars: &HashMap<&'static str, String>) -> String {
+	let mut result = String::with_capacity(arg.len());
+	let mut rest = arg;
+
+	while let Some(start) = rest.find("${") {
+		result.push_str(&rest[..start]);
+		match rest[start..].find('}') {
+			Some(end) => {
+				let key = &rest[start + 2..start + end];
+				match vars.get(key) {
+					Some(v) => result.push_str(v),
+					None => result.push_str(&rest[start..=start + end]),
+				}
+				rest = &rest[start + end + 1..];
+			}
+			None => {
+				result.push_str(&rest[start..]);
+				rest = "";
+			}
+		}
+	}
+	result.push_str(rest);
+	result
+}
+
+/// Flatten an `arguments.game` / `arguments.jvm` list, dropping entries whose
+/// rules don't apply and filling in placeholders.
+pub fn evaluate_arguments(
+	args: &[Argument],
+	env: &Environment,
+	features: &Features,
+	vars: &HashMap<&'static str, String>,
+) -> Vec<String> {
+	let mut result: Vec<String> = vec![];
+
+	for a in args {
+		match a {
+			Argument::Plain(s) => result.push(substitute(s, vars)),
+			Argument::Conditional { rules, value } => {
+				if !rules_allow(rules, env, features) {
+					continue;
+				}
+				match value {
+					ArgumentValue::Single(s) => result.push(substitute(s, vars)),
+					ArgumentValue::Many(v) => result.extend(v.iter().map(|s| substitute(s, vars))),
+				}
+			}
+		}
+	}
+
+	result
+}
+
+/// JVM arguments that old manifests leave implicit because the launcher always added them.
+fn legacy_jvm_arguments() -> Vec<Argument> {
+	vec![
+		Argument::Plain("-Djava.library.path=${natives_directory}".to_string()),
+		Argument::Plain("-cp".to_string()),
+		Argument::Plain("${classpath}".to_string()),
+	]
+}
+
+/// Resolve the JVM and game arguments for a version, handling both the modern
+/// `arguments` object and the legacy `minecraftArguments` string.
+pub fn build_arguments(
+	version: &VersionJson,
+	env: &Environment,
+	features: &Features,
+	vars: &HashMap<&'static str, String>,
+) -> Result<(Vec<String>, Vec<String>), Box<dyn std::error::Error>> {
+	if let Some(arguments) = &version.arguments {
+		return Ok((
+			evaluate_arguments(&arguments.jvm, env, features, vars),
+			evaluate_arguments(&arguments.game, env, features, vars),
+		));
+	}
+
+	let legacy = version
+		.minecraft_arguments
+		.as_ref()
+		.ok_or("Version JSON has neither arguments nor minecraftArguments")?;
+
+	Ok((
+		evaluate_arguments(&legacy_jvm_arguments(), env, features, vars),
+		legacy
+			.split_whitespace()
+			.map(|a| substitute(a, vars))
+			.collect(),
+	))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn fixture(name: &str) -> VersionJson {
+		let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+		VersionJson::parse(&std::fs::read_to_string(path).unwrap()).unwrap()
+	}
+
+	fn env(os_name: &str) -> Environment {
+		Environment {
+			os_name: os_name.to_string(),
+			os_arch: "x86_64".to_string(),
+			os_version: None,
+		}
+	}
+
+	fn features(demo: bool, custom_resolution: bool) -> Features {
+		Features::from([
+			("is_demo_user".to_string(), demo),
+			("has_custom_resolution".to_string(), custom_resolution),
+		])
+	}
+
+	fn vars() -> HashMap<&'static str, String> {
+		HashMap::from([
+			("auth_player_name", "Steve".to_string()),
+			("version_name", "1.20.1".to_string()),
+			// Has a space, which must not split the argument.
+			("game_directory", "/home/steve/My Games/vanilla".to_string()),
+			("natives_directory", "/natives".to_string()),
+			("classpath", "/a.jar:/b.jar".to_string()),
+			("resolution_width", "1280".to_string()),
+			("resolution_height", "720".to_string()),
+		])
+	}
+
+	#[test]
+	fn substitutes_placeholders() {
+		let vars = vars();
+		assert_eq!(
+			substitute("--name=${auth_player_name}!", &vars),
+			"--name=Steve!"
+		);
+		assert_eq!(
+			substitute("${version_name}-${auth_player_name}", &vars),
+			"1.20.1-Steve"
+		);
+		assert_eq!(
+			substitute("${unknown} ${version_name}", &vars),
+			"${unknown} 1.20.1"
+		);
+		assert_eq!(substitute("a ${version_name", &vars), "a ${version_name");
+		assert_eq!(substitute("${}", &vars), "${}");
+	}
+
+	#[test]
+	fn evaluates_modern_arguments() {
+		let version = fixture("1.20.1.json");
+		let arguments = version.arguments.as_ref().unwrap();
+		let vars = vars();
+
+		let game = |demo, resolution| {
+			evaluate_arguments(
+				&arguments.game,
+				&env("linux"),
+				&features(demo, resolution),
+				&vars,
+			)
+		};
+		let plain = game(false, false);
+		assert!(plain.windows(2).any(|w| w == ["--username", "Steve"]));
+		assert!(
+			plain
+				.windows(2)
+				.any(|w| w == ["--gameDir", "/home/steve/My Games/vanilla"])
+		);
+		assert!(!plain.contains(&"--demo".to_string()));
+		assert!(!plain.contains(&"--width".to_string()));
+		assert_eq!(
+			game(true, false),
+			[plain.clone(), vec!["--demo".to_string()]].concat()
+		);
+		assert!(
+			game(false, true).ends_with(&["--width", "1280", "--height", "720"].map(String::from))
+		);
+
+		let jvm = |os| evaluate_arguments(&arguments.jvm, &env(os), &features(false, false), &vars);
+		let linux = jvm("linux");
+		let osx = jvm("osx");
+		assert_eq!(
+			linux,
+			[
+				"-Djava.library.path=/natives",
+				"-Dminecraft.launcher.brand=${launcher_name}",
+				"-Dminecraft.launcher.version=${launcher_version}",
+				"-cp",
+				"/a.jar:/b.jar",
+			]
+		);
+		assert_eq!(osx[0], "-XstartOnFirstThread");
+		assert_eq!(osx[1..], linux[..]);
+	}
+
+	#[test]
+	fn builds_legacy_arguments() {
+		let version = fixture("1.12.2.json");
+		assert!(version.arguments.is_none());
+
+		let (jvm, game) =
+			build_arguments(&version, &env("linux"), &features(false, false), &vars()).unwrap();
+		assert_eq!(
+			jvm,
+			["-Djava.library.path=/natives", "-cp", "/a.jar:/b.jar"]
+		);
+		assert_eq!(game.len(), 18);
+		assert_eq!(game[..4], ["--username", "Steve", "--version", "1.20.1"]);
+		assert_eq!(game[4..6], ["--gameDir", "/home/steve/My Games/vanilla"]);
+		assert_eq!(game[6..8], ["--assetsDir", "${assets_root}"]);
+	}
+
+	#[test]
+	fn game_assets_follow_legacy_layout() {
+		let options = Options::new(
+			"Steve".to_string(),
+			"uuid".to_string(),
+			"token".to_string(),
+			"game-assets-test".to_string(),
+		);
+		let version = |id: &str| {
+			VersionJson::parse(&format!(
+				r#"{{"id": "{id}", "assetIndex": {{"id": "{id}", "url": ""}}}}"#
+			))
+			.unwrap()
+		};
+		let save_index = |version: &VersionJson, raw: &str| {
+			let path = download::asset_index_path(version);
+			std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+			std::fs::write(path, raw).unwrap();
+		};
+		let game_assets =
+			|version: &VersionJson| launch_variables(&options, version, "")["game_assets"].clone();
+		let assets_root = get_assets_folder().to_string_lossy().to_string();
+
+		let virtual_ = version("vanta-test-game-assets-virtual");
+		save_index(&virtual_, r#"{"virtual": true, "objects": {}}"#);
+		assert_eq!(
+			game_assets(&virtual_),
+			get_assets_folder()
+				.join("virtual/vanta-test-game-assets-virtual")
+				.to_string_lossy()
+		);
+
+		let resources = version("vanta-test-game-assets-resources");
+		save_index(&resources, r#"{"map_to_resources": true, "objects": {}}"#);
+		assert_eq!(
+			game_assets(&resources),
+			Path::new(&options.game_directory)
+				.join("resources")
+				.to_string_lossy()
+		);
+
+		let modern = version("vanta-test-game-assets-modern");
+		save_index(&modern, r#"{"objects": {}}"#);
+		assert_eq!(game_assets(&modern), assets_root);
+		// Without a readable index the game gets the plain assets folder.
+		assert_eq!(
+			game_assets(&version("vanta-test-game-assets-missing")),
+			assets_root
+		);
+	}
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
-use serde_json;
 
 use crate::helpers::get_instance_folder;
+use crate::rules::Features;
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -11,7 +11,8 @@ pub struct Options {
 	pub token: String,
 	pub executable_path: String,
 	pub default_executable_path: String,
-	pub jvm_arguments: Option<Vec<serde_json::Value>>,
+	/// Added after the version's own JVM arguments.
+	pub jvm_arguments: Vec<String>,
 	pub launcher_name: String,
 	pub launcher_version: String,
 	pub game_directory: String,
@@ -39,7 +40,7 @@ impl Options {
 			token,
 			executable_path: "java".to_string(),
 			default_executable_path: "java".to_string(),
-			jvm_arguments: Some(Vec::new()),
+			jvm_arguments: Vec::new(),
 			launcher_name: "vanta-launcher".to_string(),
 			launcher_version: "1.0".to_string(),
 			game_directory: get_instance_folder(&inst_id).to_str().unwrap().to_string(),
@@ -63,4 +64,28 @@ impl Options {
 			quick_play_realms: None,
 		}
 	}
+
+	/// Feature flags that `arguments` rules are checked against.
+	pub fn features(&self) -> Features {
+		Features::from([
+			("is_demo_user".to_string(), self.demo),
+			("has_custom_resolution".to_string(), self.custom_resolution),
+			(
+				"has_quick_plays_support".to_string(),
+				self.quick_play_path.is_some(),
+			),
+			(
+				"is_quick_play_singleplayer".to_string(),
+				self.quick_play_singleplayer.is_some(),
+			),
+			(
+				"is_quick_play_multiplayer".to_string(),
+				self.quick_play_multiplayer.is_some(),
+			),
+			(
+				"is_quick_play_realms".to_string(),
+				self.quick_play_realms.is_some(),
+			),
+		])
+	}
 }
@@ -1,51 +1,109 @@
-use crate::helpers::{self, get_instance_folder};
+use std::{path::Path, process::Command, time::Instant};
+
+use crate::config;
+use crate::download;
+use crate::helpers;
+use crate::instance::{self, Instance};
+use crate::java::{self, JavaSpec};
+use crate::rules::Environment;
+use crate::runtime;
+use crate::version::{self, VersionJson};
 
 mod lc_helpers;
 mod lc_types;
 
-pub fn launch(id: String, uid: String) {
-	let inst_dir = get_instance_folder(&id);
+/// Launch an instance as the given user, or the default account, and wait for
+/// the game to exit.
+/// Returns the exit code of the game process.
+pub fn launch(id: String, uid: Option<String>) -> Result<i32, Box<dyn std::error::Error>> {
+	let instance = Instance::load(&id)?;
+	let inst_dir = instance.dir();
+	let config = config::get();
+	let uid = uid.or_else(|| config.default_account.clone()).ok_or(
+		"No account given and no default_account set, see `vanta config set default_account`",
+	)?;
 	let users = helpers::read_users();
 	let user = users
 		.iter()
 		.find(|u| uid == u.name)
-		.expect("User not found.");
+		.ok_or("User not found.")?;
+
+	let version = version::read_instance_version(&inst_dir)?;
 
-	let options = lc_types::Options::new(
+	let mut options = lc_types::Options::new(
 		user.name.clone(),
 		user.id.clone(),
 		user.access_token.clone(),
-		inst_dir.to_str().unwrap().to_string(),
+		id.clone(),
 	);
+	options.executable_path = java_executable(&instance, &version)?;
+	// Only a GC preset needs to know the release, and asking may start Java.
+	let java_major = instance
+		.gc_preset
+		.as_ref()
+		.and_then(|_| java::major_of(Path::new(&options.executable_path)));
+	options.jvm_arguments = instance.launch_jvm_args(config, java_major)?;
+
+	let main_class = version
+		.main_class
+		.as_deref()
+		.ok_or("Version JSON has no mainClass")?;
+
+	let env = Environment::host();
+	// Instances installed before natives were extracted at install time.
+	if !inst_dir.join("versions/natives").exists() {
+		download::extract_natives(&version, &inst_dir, &env)?;
+	}
 
-	let mut command: Vec<String> = vec![];
+	let classpath = lc_helpers::build_classpath(&version, &inst_dir, &env);
+	let features = options.features();
+	let vars = lc_helpers::launch_variables(&options, &version, &classpath);
+	let (jvm_args, game_args) = lc_helpers::build_arguments(&version, &env, &features, &vars)?;
 
-	command.push(options.default_executable_path);
+	let mut command = Command::new(&options.executable_path);
 
-	if let Some(jvm_args) = options.jvm_arguments {
-		command.extend(
-			jvm_args
-				.iter()
-				.map(|v| v.to_string())
-				.collect::<Vec<String>>(),
-		);
+	command.args(jvm_args).args(&options.jvm_arguments);
+
+	command
+		.arg(main_class)
+		.args(game_args)
+		.args(&instance.game_args)
+		.envs(&instance.env)
+		.current_dir(&inst_dir);
+
+	println!("Launching {} as {}...", instance.name, options.username);
+	let started = Instant::now();
+	let last_played = instance::now();
+	let status = command
+		.status()
+		.map_err(|e| format!("Failed to start {}: {}", options.executable_path, e))?;
+
+	// The instance may have been configured while the game ran; keep those changes.
+	let saved = Instance::load(&id).and_then(|mut instance| {
+		instance.last_played = Some(last_played);
+		instance.playtime += started.elapsed().as_secs();
+		instance.save()
+	});
+	if let Err(e) = saved {
+		eprintln!("WARN: Failed to save playtime of {}: {}", id, e);
 	}
-	// yeah dude fuck launching the game i can't do this shit
 
-	dbg!(command);
+	match status.code() {
+		Some(code) => Ok(code),
+		None => Err("Minecraft was terminated by a signal".into()),
+	}
 }
 
-pub fn list_instances(stdout: bool) -> Vec<String> {
-	let inst_dir = get_instance_folder("");
-	let mut result: Vec<String> = vec![];
-	for inst in inst_dir.read_dir().expect("read_dir call failed") {
-		if let Ok(inst) = inst {
-			let filename = inst.file_name().to_str().unwrap().to_string();
-			if stdout {
-				println!("{}", filename);
-			}
-			result.push(filename);
-		}
+/// The Java executable an instance's game and installer tools are run with:
+/// the instance's own choice or the configured one, else the Mojang runtime
+/// `version` asks for (installed on first use), else `java` on the `PATH`.
+pub fn java_executable(
+	instance: &Instance,
+	version: &VersionJson,
+) -> Result<String, Box<dyn std::error::Error>> {
+	if let Some(java) = config::get().java_for(instance) {
+		return java::resolve(&JavaSpec::parse(java));
 	}
-	return result;
+	Ok(runtime::provision_now(version)?
+		.map_or_else(|| "java".to_string(), |p| p.to_string_lossy().to_string()))
 }
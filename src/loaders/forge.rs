@@ -0,0 +1,579 @@
+use std::{
+	collections::HashMap,
+	fs::{self, File},
+	io::{self, Read},
+	path::{Path, PathBuf},
+	process::Command,
+};
+
+use regex::Regex;
+use reqwest::Client;
+use serde::Deserialize;
+use zip::ZipArchive;
+
+use crate::download::{self, DownloadEntry};
+use crate::helpers::get_libraries_folder;
+use crate::version::{Library, VersionJson};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flavor {
+	Forge,
+	NeoForge,
+}
+
+impl Flavor {
+	/// The flavor a `vanta modloader` loader name refers to, if it is a Forge one.
+	pub fn from_loader(loader: &str) -> Option<Self> {
+		match loader {
+			"forge" => Some(Flavor::Forge),
+			"neoforge" => Some(Flavor::NeoForge),
+			_ => None,
+		}
+	}
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Flavor::Forge => "Forge",
+			Flavor::NeoForge => "NeoForge",
+		}
+	}
+
+	/// Maven coordinate of the installer jar for a full loader version.
+	fn installer_coordinate(&self, version: &str) -> String {
+		match self {
+			Flavor::Forge => format!("net.minecraftforge:forge:{}:installer", version),
+			Flavor::NeoForge => format!("net.neoforged:neoforge:{}:installer", version),
+		}
+	}
+
+	fn maven(&self) -> &'static str {
+		match self {
+			Flavor::Forge => "https://maven.minecraftforge.net/",
+			Flavor::NeoForge => "https://maven.neoforged.net/releases/",
+		}
+	}
+}
+
+/// `install_profile.json` of a Forge/NeoForge installer (spec 1, Minecraft 1.13+).
+#[derive(Debug, Deserialize)]
+pub struct InstallProfile {
+	/// Where the launch profile lives inside the installer, e.g. `/version.json`.
+	pub json: String,
+	pub minecraft: String,
+	#[serde(default)]
+	pub data: HashMap<String, SidedValue>,
+	#[serde(default)]
+	pub processors: Vec<Processor>,
+	/// Libraries the processors need; the game itself doesn't.
+	#[serde(default)]
+	pub libraries: Vec<Library>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SidedValue {
+	pub client: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Processor {
+	/// Processors without `sides` run for both.
+	#[serde(default)]
+	pub sides: Option<Vec<String>>,
+	pub jar: String,
+	#[serde(default)]
+	pub classpath: Vec<String>,
+	#[serde(default)]
+	pub args: Vec<String>,
+	/// Files the processor writes and the SHA-1 each must have afterwards.
+	#[serde(default)]
+	pub outputs: HashMap<String, String>,
+}
+
+/// Parse an `install_profile.json`, telling the legacy format of Forge for
+/// Minecraft 1.12.2 and older (its launch profile inline as `versionInfo`, no
+/// processors) apart from a broken file.
+fn parse_install_profile(raw: &str) -> Result<InstallProfile, Box<dyn std::error::Error>> {
+	let value: serde_json::Value =
+		serde_json::from_str(raw).map_err(|e| format!("Invalid install_profile.json: {}", e))?;
+	if value.get("versionInfo").is_some() {
+		return Err(
+			"Legacy Forge is unsupported: installers for Minecraft 1.12.2 and older use the old install_profile.json format"
+				.into(),
+		);
+	}
+	serde_json::from_value(value).map_err(|e| format!("Invalid install_profile.json: {}", e).into())
+}
+
+/// Versions of a loader available for `game_version`, oldest first.
+pub async fn loader_versions(
+	flavor: Flavor,
+	game_version: &str,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+	let mut versions: Vec<String> = match flavor {
+		Flavor::Forge => {
+			let raw = get_text(
+				"https://maven.minecraftforge.net/net/minecraftforge/forge/maven-metadata.xml",
+			)
+			.await?;
+			let re = Regex::new(r"<version>([^<]+)</version>")?;
+			let prefix = format!("{}-", game_version);
+			re.captures_iter(&raw)
+				.map(|c| c[1].to_string())
+				.filter(|v| v.starts_with(&prefix))
+				.collect()
+		}
+		Flavor::NeoForge => {
+			#[derive(Deserialize)]
+			struct Versions {
+				versions: Vec<String>,
+			}
+			let raw = get_text(
+				"https://maven.neoforged.net/api/maven/versions/releases/net/neoforged/neoforge",
+			)
+			.await?;
+			let prefix = neoforge_prefix(game_version).ok_or(format!(
+				"NeoForge does not support Minecraft {}",
+				game_version
+			))?;
+			serde_json::from_str::<Versions>(&raw)
+				.map_err(|e| format!("Invalid NeoForge version list: {}", e))?
+				.versions
+				.into_iter()
+				.filter(|v| v.starts_with(&prefix))
+				.collect()
+		}
+	};
+
+	if versions.is_empty() {
+		return Err(format!(
+			"{} does not support Minecraft {}",
+			flavor.name(),
+			game_version
+		)
+		.into());
+	}
+	// Neither list promises an order, so don't rely on the one they come in.
+	versions.sort_by_cached_key(|v| version_key(v));
+	Ok(versions)
+}
+
+/// Sort key for a loader version: its leading numbers, e.g. `[1, 20, 1, 47, 1, 3]`
+/// for `1.20.1-47.1.3`. Suffixes such as `-beta` don't take part.
+fn version_key(version: &str) -> Vec<u64> {
+	version
+		.split(['.', '-'])
+		.map_while(|part| part.parse().ok())
+		.collect()
+}
+
+/// NeoForge numbers its releases after the Minecraft version: 1.20.4 is `20.4.x`, 1.21 is `21.0.x`.
+fn neoforge_prefix(game_version: &str) -> Option<String> {
+	let mut parts = game_version.strip_prefix("1.")?.split('.');
+	let major = parts.next()?;
+	let minor = parts.next().unwrap_or("0");
+	Some(format!("{}.{}.", major, minor))
+}
+
+async fn get_text(url: &str) -> Result<String, Box<dyn std::error::Error>> {
+	Ok(Client::new()
+		.get(url)
+		.send()
+		.await?
+		.error_for_status()?
+		.text()
+		.await?)
+}
+
+/// The full loader version and its installer jar. Without a `loader_version`
+/// the newest stable release for `game_version` is picked.
+pub async fn installer(
+	flavor: Flavor,
+	game_version: &str,
+	loader_version: Option<&str>,
+) -> Result<(String, DownloadEntry), Box<dyn std::error::Error>> {
+	let version = match (flavor, loader_version) {
+		// Forge versions are `<minecraft>-<forge>`, but people usually only type the latter.
+		(Flavor::Forge, Some(v)) if !v.starts_with(&format!("{}-", game_version)) => {
+			format!("{}-{}", game_version, v)
+		}
+		(_, Some(v)) => v.to_string(),
+		(_, None) => {
+			let versions = loader_versions(flavor, game_version).await?;
+			versions
+				.iter()
+				.rev()
+				.find(|v| !v.contains("beta"))
+				.or(versions.last())
+				.cloned()
+				.ok_or(format!("No {} release found", flavor.name()))?
+		}
+	};
+
+	let path = download::maven_path(&flavor.installer_coordinate(&version))
+		.ok_or("Invalid loader version")?;
+	let url = format!("{}{}", flavor.maven(), path);
+	// Maven keeps the checksum next to the jar; some files hold `<sha1>  <name>`.
+	let sha1 = get_text(&format!("{}.sha1", url))
+		.await
+		.map_err(|e| format!("Failed to fetch the checksum of {}: {}", url, e))?;
+	let sha1 = sha1
+		.split_whitespace()
+		.next()
+		.filter(|s| s.len() == 40 && s.chars().all(|c| c.is_ascii_hexdigit()))
+		.ok_or(format!("Invalid checksum for {}", url))?
+		.to_lowercase();
+	let entry = DownloadEntry {
+		url,
+		destination: format!("libraries/{}", path),
+		size: None,
+		sha1: Some(sha1),
+		name: Some(format!("{} {} installer", flavor.name(), version)),
+		shared: true,
+	};
+	Ok((version, entry))
+}
+
+/// An opened installer jar.
+pub struct Installer {
+	path: PathBuf,
+	archive: ZipArchive<File>,
+}
+
+impl Installer {
+	pub fn open(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+		let archive = File::open(path)
+			.map_err(|e| e.to_string())
+			.and_then(|f| ZipArchive::new(f).map_err(|e| e.to_string()))
+			.map_err(|e| format!("Failed to open installer {}: {}", path.display(), e))?;
+		Ok(Self {
+			path: path.to_path_buf(),
+			archive,
+		})
+	}
+
+	fn read(&mut self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+		let mut raw = String::new();
+		self.archive
+			.by_name(name.trim_start_matches('/'))
+			.map_err(|e| format!("Installer has no {}: {}", name, e))?
+			.read_to_string(&mut raw)?;
+		Ok(raw)
+	}
+
+	/// Copy a file out of the installer, unless it is already there.
+	fn extract(&mut self, name: &str, dest: &Path) -> Result<(), Box<dyn std::error::Error>> {
+		let mut file = self
+			.archive
+			.by_name(name.trim_start_matches('/'))
+			.map_err(|e| format!("Installer has no {}: {}", name, e))?;
+		if fs::metadata(dest).is_ok_and(|m| m.len() == file.size()) {
+			return Ok(());
+		}
+		if let Some(p) = dest.parent() {
+			fs::create_dir_all(p)?;
+		}
+		io::copy(&mut file, &mut File::create(dest)?)?;
+		Ok(())
+	}
+
+	pub fn install_profile(&mut self) -> Result<InstallProfile, Box<dyn std::error::Error>> {
+		parse_install_profile(&self.read("install_profile.json")?)
+	}
+
+	pub fn version_json(
+		&mut self,
+		profile: &InstallProfile,
+	) -> Result<VersionJson, Box<dyn std::error::Error>> {
+		VersionJson::parse(&self.read(&profile.json)?)
+	}
+
+	/// Copy libraries that ship inside the installer (those without a download
+	/// URL) from its `maven/` folder into the libraries folder.
+	pub fn extract_libraries(
+		&mut self,
+		libraries: &[Library],
+	) -> Result<(), Box<dyn std::error::Error>> {
+		for l in libraries {
+			let Some(path) = download::library_artifact(l)
+				.filter(|a| a.url.is_empty())
+				.and_then(|a| a.path)
+			else {
+				continue;
+			};
+			let name = format!("maven/{}", path);
+			if self.archive.index_for_name(&name).is_some() {
+				self.extract(&name, &get_libraries_folder().join(&path))?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Resolve `data` for the client side, extracting files it points into the installer to `work_dir`.
+	pub fn data(
+		&mut self,
+		profile: &InstallProfile,
+		builtins: HashMap<String, String>,
+		work_dir: &Path,
+	) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+		let mut data = builtins;
+		data.insert(
+			"INSTALLER".to_string(),
+			self.path.to_string_lossy().to_string(),
+		);
+		for (key, value) in &profile.data {
+			let resolved = if value.client.starts_with('/') {
+				let dest = work_dir.join(value.client.trim_start_matches('/'));
+				self.extract(&value.client, &dest)?;
+				dest.to_string_lossy().to_string()
+			} else {
+				resolve(&value.client, &HashMap::new())?
+			};
+			data.insert(key.clone(), resolved);
+		}
+		Ok(data)
+	}
+}
+
+/// Resolve one processor token: `[coordinate]` is a library path, `'text'` a
+/// literal, and `{KEY}` anywhere in the token is replaced from `data`.
+pub fn resolve(
+	token: &str,
+	data: &HashMap<String, String>,
+) -> Result<String, Box<dyn std::error::Error>> {
+	if let Some(coordinate) = token.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+		let path = download::maven_path(coordinate)
+			.ok_or(format!("Invalid Maven coordinate {}", coordinate))?;
+		return Ok(get_libraries_folder()
+			.join(path)
+			.to_string_lossy()
+			.to_string());
+	}
+	if let Some(literal) = token.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+		return Ok(literal.to_string());
+	}
+
+	let mut result = String::with_capacity(token.len());
+	let mut rest = token;
+	while let Some(start) = rest.find('{') {
+		let Some(end) = rest[start..].find('}') else {
+			break;
+		};
+		let key = &rest[start + 1..start + end];
+		result.push_str(&rest[..start]);
+		result.push_str(
+			data.get(key)
+				.ok_or(format!("Processor uses unknown data {{{}}}", key))?,
+		);
+		rest = &rest[start + end + 1..];
+	}
+	result.push_str(rest);
+	Ok(result)
+}
+
+/// `Main-Class` from a jar's manifest.
+fn main_class(jar: &Path) -> Result<String, Box<dyn std::error::Error>> {
+	let mut archive = ZipArchive::new(
+		File::open(jar).map_err(|e| format!("Missing processor {}: {}", jar.display(), e))?,
+	)?;
+	let mut manifest = String::new();
+	archive
+		.by_name("META-INF/MANIFEST.MF")?
+		.read_to_string(&mut manifest)?;
+	manifest
+		.lines()
+		.find_map(|l| l.strip_prefix("Main-Class:"))
+		.map(|c| c.trim().to_string())
+		.ok_or(format!("{} has no Main-Class", jar.display()).into())
+}
+
+/// Whether every declared output of a processor exists with its expected hash.
+fn outputs_valid(
+	processor: &Processor,
+	data: &HashMap<String, String>,
+) -> Result<bool, Box<dyn std::error::Error>> {
+	if processor.outputs.is_empty() {
+		return Ok(false);
+	}
+	for (file, hash) in &processor.outputs {
+		let path = resolve(file, data)?;
+		let expected = resolve(hash, data)?;
+		if !download::sha1_file(Path::new(&path)).is_ok_and(|h| h.eq_ignore_ascii_case(&expected)) {
+			return Ok(false);
+		}
+	}
+	Ok(true)
+}
+
+/// Run the client-side processors of an install profile with `java`, skipping
+/// ones whose outputs are already in place and checking the outputs of the rest.
+/// Returns the paths of every output, which no version JSON lists.
+pub fn run_processors(
+	profile: &InstallProfile,
+	data: &HashMap<String, String>,
+	java: &str,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+	let mut outputs = vec![];
+	let client_side = profile.processors.iter().filter(|p| {
+		p.sides
+			.as_ref()
+			.is_none_or(|s| s.iter().any(|s| s == "client"))
+	});
+
+	for (i, processor) in client_side.enumerate() {
+		for file in processor.outputs.keys() {
+			outputs.push(PathBuf::from(resolve(file, data)?));
+		}
+		if outputs_valid(processor, data)? {
+			continue;
+		}
+
+		let jar = PathBuf::from(resolve(&format!("[{}]", processor.jar), data)?);
+		let mut classpath = vec![jar.clone()];
+		for c in &processor.classpath {
+			classpath.push(PathBuf::from(resolve(&format!("[{}]", c), data)?));
+		}
+		let args = processor
+			.args
+			.iter()
+			.map(|a| resolve(a, data))
+			.collect::<Result<Vec<String>, _>>()?;
+
+		println!("Running processor {} ({})", i + 1, processor.jar);
+		let status = Command::new(java)
+			.arg("-cp")
+			.arg(std::env::join_paths(&classpath)?)
+			.arg(main_class(&jar)?)
+			.args(&args)
+			.status()
+			.map_err(|e| format!("Failed to start {}: {}", java, e))?;
+		if !status.success() {
+			return Err(format!("Processor {} failed with {}", processor.jar, status).into());
+		}
+
+		for (file, hash) in &processor.outputs {
+			let path = resolve(file, data)?;
+			let expected = resolve(hash, data)?;
+			let actual = download::sha1_file(Path::new(&path)).map_err(|e| {
+				format!("Processor {} did not write {}: {}", processor.jar, path, e)
+			})?;
+			if !actual.eq_ignore_ascii_case(&expected) {
+				_ = fs::remove_file(&path);
+				return Err(format!(
+					"Processor {} wrote {} with SHA-1 {}, expected {}",
+					processor.jar, path, actual, expected
+				)
+				.into());
+			}
+		}
+	}
+
+	Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn resolves_processor_tokens() {
+		let data = HashMap::from([
+			("SIDE".to_string(), "client".to_string()),
+			("MAPPINGS".to_string(), "/tmp/mappings.txt".to_string()),
+		]);
+
+		assert_eq!(resolve("'abc'", &data).unwrap(), "abc");
+		assert_eq!(resolve("{SIDE}", &data).unwrap(), "client");
+		assert_eq!(
+			resolve("--map={MAPPINGS}", &data).unwrap(),
+			"--map=/tmp/mappings.txt"
+		);
+		assert!(
+			resolve("[net.minecraftforge:binarypatcher:1.1.1]", &data)
+				.unwrap()
+				.ends_with("net/minecraftforge/binarypatcher/1.1.1/binarypatcher-1.1.1.jar")
+		);
+		assert!(resolve("{MISSING}", &data).is_err());
+	}
+
+	#[test]
+	fn sorts_versions_numerically() {
+		let mut versions = vec![
+			"1.20.1-47.1.10",
+			"1.20.1-47.1.3",
+			"1.20.1-47.10.0",
+			"1.20.1-47.2.0",
+		];
+		versions.sort_by_cached_key(|v| version_key(v));
+		assert_eq!(
+			versions,
+			[
+				"1.20.1-47.1.3",
+				"1.20.1-47.1.10",
+				"1.20.1-47.2.0",
+				"1.20.1-47.10.0",
+			]
+		);
+
+		let mut versions = vec!["20.4.237", "20.4.80-beta", "20.4.9-beta"];
+		versions.sort_by_cached_key(|v| version_key(v));
+		assert_eq!(versions, ["20.4.9-beta", "20.4.80-beta", "20.4.237"]);
+	}
+
+	#[test]
+	fn neoforge_versions_follow_minecraft() {
+		assert_eq!(neoforge_prefix("1.20.4").as_deref(), Some("20.4."));
+		assert_eq!(neoforge_prefix("1.21").as_deref(), Some("21.0."));
+		assert_eq!(neoforge_prefix("24w14a"), None);
+	}
+
+	#[test]
+	fn rejects_legacy_install_profile() {
+		let err = parse_install_profile(
+			r#"{
+				"install": {
+					"profileName": "forge",
+					"target": "1.12.2-forge1.12.2-14.23.5.2860",
+					"path": "net.minecraftforge:forge:1.12.2-14.23.5.2860",
+					"filePath": "forge-1.12.2-14.23.5.2860-universal.jar",
+					"minecraft": "1.12.2"
+				},
+				"versionInfo": {"id": "1.12.2-forge1.12.2-14.23.5.2860", "libraries": []}
+			}"#,
+		)
+		.unwrap_err();
+		assert!(err.to_string().starts_with("Legacy Forge is unsupported"));
+	}
+
+	#[test]
+	fn parses_install_profile() {
+		let profile = parse_install_profile(
+			r#"{
+				"spec": 1,
+				"profile": "forge",
+				"version": "1.20.1-forge-47.2.0",
+				"json": "/version.json",
+				"path": "net.minecraftforge:forge:1.20.1-47.2.0",
+				"minecraft": "1.20.1",
+				"data": {
+					"BINPATCH": {"client": "/data/client.lzma", "server": "/data/server.lzma"},
+					"PATCHED_SHA": {"client": "'0123abcd'", "server": "'4567ef01'"}
+				},
+				"processors": [
+					{"sides": ["server"], "jar": "a:b:1", "args": []},
+					{
+						"jar": "net.minecraftforge:binarypatcher:1.1.1",
+						"classpath": ["com.github.jponge:lzma-java:1.3"],
+						"args": ["--clean", "{MINECRAFT_JAR}", "--apply", "{BINPATCH}"],
+						"outputs": {"{PATCHED}": "{PATCHED_SHA}"}
+					}
+				],
+				"libraries": [{"name": "net.minecraftforge:binarypatcher:1.1.1"}]
+			}"#,
+		)
+		.unwrap();
+		assert_eq!(profile.minecraft, "1.20.1");
+		assert_eq!(profile.processors.len(), 2);
+		assert_eq!(profile.data["PATCHED_SHA"].client, "'0123abcd'");
+		assert_eq!(profile.processors[1].outputs["{PATCHED}"], "{PATCHED_SHA}");
+	}
+}
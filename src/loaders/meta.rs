@@ -0,0 +1,120 @@
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::version::VersionJson;
+
+/// A Fabric-style loader meta API. Quilt forked Fabric's and kept its shape.
+pub struct Meta {
+	pub name: &'static str,
+	pub url: &'static str,
+}
+
+pub const FABRIC: Meta = Meta {
+	name: "Fabric",
+	url: "https://meta.fabricmc.net/v2",
+};
+
+pub const QUILT: Meta = Meta {
+	name: "Quilt",
+	url: "https://meta.quiltmc.org/v3",
+};
+
+#[derive(Debug, Deserialize)]
+struct LoaderEntry {
+	loader: LoaderVersion,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoaderVersion {
+	pub version: String,
+	/// Only Fabric says; Quilt marks pre-releases with a suffix like `-beta.1`.
+	#[serde(default)]
+	stable: Option<bool>,
+}
+
+impl LoaderVersion {
+	pub fn is_stable(&self) -> bool {
+		self.stable.unwrap_or(!self.version.contains('-'))
+	}
+}
+
+impl Meta {
+	/// Loader versions that support `game_version`, newest first.
+	pub async fn loader_versions(
+		&self,
+		game_version: &str,
+	) -> Result<Vec<LoaderVersion>, Box<dyn std::error::Error>> {
+		let raw = Client::new()
+			.get(format!("{}/versions/loader/{}", self.url, game_version))
+			.send()
+			.await?
+			.error_for_status()?
+			.text()
+			.await?;
+		let entries = serde_json::from_str::<Vec<LoaderEntry>>(&raw)
+			.map_err(|e| format!("Invalid {} loader list: {}", self.name, e))?;
+		if entries.is_empty() {
+			return Err(
+				format!("{} does not support Minecraft {}", self.name, game_version).into(),
+			);
+		}
+		Ok(entries.into_iter().map(|e| e.loader).collect())
+	}
+
+	/// The launch profile of a loader version for `game_version`, and that
+	/// version. Without a `loader_version`, the newest stable loader is used.
+	pub async fn profile(
+		&self,
+		game_version: &str,
+		loader_version: Option<&str>,
+	) -> Result<(String, VersionJson), Box<dyn std::error::Error>> {
+		let loader_version = match loader_version {
+			Some(v) => v.to_string(),
+			None => {
+				self.loader_versions(game_version)
+					.await?
+					.into_iter()
+					.find(|l| l.is_stable())
+					.ok_or(format!("No stable {} loader found", self.name))?
+					.version
+			}
+		};
+
+		let resp = Client::new()
+			.get(format!(
+				"{}/versions/loader/{}/{}/profile/json",
+				self.url, game_version, loader_version
+			))
+			.send()
+			.await?;
+		if resp.status().is_client_error() {
+			return Err(format!(
+				"{} loader {} is not available for Minecraft {}",
+				self.name, loader_version, game_version
+			)
+			.into());
+		}
+		let profile = VersionJson::parse(&resp.error_for_status()?.text().await?)?;
+		Ok((loader_version, profile))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn stability_of_fabric_and_quilt_versions() {
+		let versions: Vec<LoaderEntry> = serde_json::from_str(
+			r#"[
+				{"loader": {"version": "0.15.11", "stable": true}},
+				{"loader": {"version": "0.15.0", "stable": false}},
+				{"loader": {"version": "0.26.0-beta.1"}},
+				{"loader": {"version": "0.25.0"}}
+			]"#,
+		)
+		.unwrap();
+		let stable: Vec<bool> = versions.iter().map(|e| e.loader.is_stable()).collect();
+		assert_eq!(stable, [true, false, false, true]);
+	}
+}
@@ -0,0 +1,198 @@
+use std::{
+	collections::HashMap,
+	fs,
+	path::{Path, PathBuf},
+};
+
+use tokio::runtime::Runtime;
+
+use crate::download;
+use crate::helpers::{self, get_libraries_folder};
+use crate::instance::Instance;
+use crate::launch;
+use crate::rules::Environment;
+use crate::version::{self, VersionJson};
+
+mod forge;
+mod meta;
+
+use forge::Flavor;
+
+/// The meta API serving a loader's profiles.
+fn meta(loader: &str) -> Result<&'static meta::Meta, Box<dyn std::error::Error>> {
+	match loader {
+		"fabric" => Ok(&meta::FABRIC),
+		"quilt" => Ok(&meta::QUILT),
+		other => Err(format!("Unknown mod loader {}", other).into()),
+	}
+}
+
+/// The vanilla version an instance was created with.
+fn game_version(
+	instance: &str,
+	inst_dir: &Path,
+) -> Result<VersionJson, Box<dyn std::error::Error>> {
+	VersionJson::read(&inst_dir.join(version::CLIENT_JSON))
+		.map_err(|e| format!("Instance {} has no Minecraft installed: {}", instance, e).into())
+}
+
+/// Install a mod loader into an instance, replacing any loader it already has.
+pub fn install(
+	instance: String,
+	loader: String,
+	loader_version: Option<String>,
+	parallel: usize,
+	retries: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+	let mut instance = Instance::load(&instance)?;
+	let inst_dir = instance.dir();
+	let game = game_version(&instance.id, &inst_dir)?;
+	let rt = Runtime::new()?;
+
+	let (loader_version, profile, outputs) = match Flavor::from_loader(&loader) {
+		Some(flavor) => install_forge(
+			&rt,
+			flavor,
+			&instance,
+			&game,
+			loader_version,
+			parallel,
+			retries,
+		)?,
+		None => {
+			let (loader_version, profile) =
+				rt.block_on(meta(&loader)?.profile(&game.id, loader_version.as_deref()))?;
+			(loader_version, profile, vec![])
+		}
+	};
+	if profile.inherits_from.as_deref() != Some(game.id.as_str()) {
+		return Err(format!("{} does not inherit from Minecraft {}", profile.id, game.id).into());
+	}
+
+	println!("Installing {}...", profile.id);
+	let queue = download::library_downloads(&profile.libraries, &Environment::host());
+	download::download_queue(&rt, queue, &inst_dir, parallel, retries)?;
+
+	// Only written once the libraries are in place, so launch never sees a half-installed loader.
+	helpers::write_bytes(
+		inst_dir
+			.join(version::LOADER_JSON)
+			.to_string_lossy()
+			.to_string(),
+		serde_json::to_string(&profile)?.as_bytes(),
+	)?;
+	fs::create_dir_all(inst_dir.join("mods"))?;
+	instance.loader_name = loader;
+	instance.loader_version = Some(loader_version);
+	instance.jar_version = profile.id.clone();
+	instance.installer_outputs = outputs
+		.iter()
+		.filter_map(|p| p.strip_prefix(get_libraries_folder()).ok())
+		.map(|p| p.to_string_lossy().to_string())
+		.collect();
+	instance.save()?;
+
+	println!("Installed {}.", profile.id);
+	Ok(())
+}
+
+/// Run a Forge or NeoForge installer against an instance: fetch the installer,
+/// the libraries its processors need and run them to patch the game. Returns
+/// the loader version, launch profile and the files the processors wrote; the
+/// profile's own libraries are left for the caller to download.
+fn install_forge(
+	rt: &Runtime,
+	flavor: Flavor,
+	instance: &Instance,
+	game: &VersionJson,
+	loader_version: Option<String>,
+	parallel: usize,
+	retries: usize,
+) -> Result<(String, VersionJson, Vec<PathBuf>), Box<dyn std::error::Error>> {
+	let inst_dir = &instance.dir();
+	let (loader_version, entry) = rt.block_on(forge::installer(
+		flavor,
+		&game.id,
+		loader_version.as_deref(),
+	))?;
+	let installer_path = download::entry_path(&entry, inst_dir);
+	download::download_queue(rt, vec![entry], inst_dir, parallel, retries)?;
+
+	let mut installer = forge::Installer::open(&installer_path)?;
+	let install_profile = installer.install_profile()?;
+	if install_profile.minecraft != game.id {
+		return Err(format!(
+			"This {} installer is for Minecraft {}, the instance has {}",
+			flavor.name(),
+			install_profile.minecraft,
+			game.id
+		)
+		.into());
+	}
+	let profile = installer.version_json(&install_profile)?;
+	installer.extract_libraries(&install_profile.libraries)?;
+	installer.extract_libraries(&profile.libraries)?;
+
+	let queue = download::library_downloads(&install_profile.libraries, &Environment::host());
+	download::download_queue(rt, queue, inst_dir, parallel, retries)?;
+
+	let path = |p: &Path| p.to_string_lossy().to_string();
+	let builtins = HashMap::from([
+		("SIDE".to_string(), "client".to_string()),
+		(
+			"MINECRAFT_JAR".to_string(),
+			path(&inst_dir.join("versions/client.jar")),
+		),
+		("MINECRAFT_VERSION".to_string(), game.id.clone()),
+		("ROOT".to_string(), path(inst_dir)),
+		("LIBRARY_DIR".to_string(), path(&get_libraries_folder())),
+	]);
+	let work_dir = inst_dir.join(".installer");
+	let result = installer
+		.data(&install_profile, builtins, &work_dir)
+		.and_then(|data| {
+			forge::run_processors(
+				&install_profile,
+				&data,
+				&launch::java_executable(instance, game)?,
+			)
+		});
+	_ = fs::remove_dir_all(&work_dir);
+
+	Ok((loader_version, profile, result?))
+}
+
+/// Print the loader versions available for an instance's Minecraft version.
+pub fn list_versions(instance: String, loader: String) -> Result<(), Box<dyn std::error::Error>> {
+	let instance = Instance::load(&instance)?;
+	let game = game_version(&instance.id, &instance.dir())?;
+	let rt = Runtime::new()?;
+
+	let versions: Vec<(String, bool)> = match Flavor::from_loader(&loader) {
+		Some(flavor) => rt
+			.block_on(forge::loader_versions(flavor, &game.id))?
+			.into_iter()
+			.rev()
+			.map(|v| {
+				let stable = !v.contains("beta");
+				(v, stable)
+			})
+			.collect(),
+		None => rt
+			.block_on(meta(&loader)?.loader_versions(&game.id))?
+			.into_iter()
+			.map(|v| {
+				let stable = v.is_stable();
+				(v.version, stable)
+			})
+			.collect(),
+	};
+	for (version, stable) in versions {
+		println!(
+			"{:<20} {}",
+			version,
+			if stable { "stable" } else { "unstable" }
+		);
+	}
+	Ok(())
+}
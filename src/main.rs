@@ -1,13 +1,33 @@
 mod args;
 mod authentication;
+mod config;
 mod constants;
 mod download;
 mod helpers;
+mod instance;
+mod java;
 mod launch;
+mod loaders;
+mod progress;
+mod rules;
+mod runtime;
+mod store;
+mod version;
 use clap::Parser;
 
 fn main() {
 	let cli: args::Cli = args::Cli::parse();
+	progress::set_quiet(cli.quiet);
+	if let Err(e) = config::init(&cli.config) {
+		// A broken config file can still be fixed with `vanta config`.
+		if !matches!(cli.sub, args::SubCmd::Config(_)) {
+			eprintln!("ERROR: {}", e);
+			std::process::exit(1);
+		}
+		eprintln!("WARN: {}", e);
+		config::init_with(config::Config::default());
+	}
+	let config = config::get();
 	match cli.sub {
 		args::SubCmd::User(user_cmd) => match user_cmd.cmd {
 			args::UserSub::Login => authentication::login(),
@@ -18,18 +38,165 @@ fn main() {
 			args::InstanceSub::VersionList => {
 				download::list_versions().expect("Failed to list versions")
 			}
-			args::InstanceSub::List => {
-				launch::list_instances(true);
+			args::InstanceSub::List {
+				group,
+				loader,
+				version,
+				json,
+			} => {
+				let filter = instance::ListFilter {
+					group,
+					loader,
+					version,
+				};
+				if let Err(e) = instance::list(&filter, json) {
+					eprintln!("ERROR: {}", e);
+					std::process::exit(1);
+				}
 			}
-			args::InstanceSub::Run { id, uid } => launch::launch(id, uid),
+			args::InstanceSub::Run { id, uid } => match launch::launch(id, uid) {
+				Ok(0) => (),
+				Ok(code) => {
+					eprintln!("ERROR: Minecraft exited with code {}", code);
+					std::process::exit(code);
+				}
+				Err(e) => {
+					eprintln!("ERROR: {}", e);
+					std::process::exit(1);
+				}
+			},
 			args::InstanceSub::New {
 				id,
 				version,
 				parallel,
-			} => download::new_instance(version, id, parallel),
+				retries,
+			} => {
+				if let Err(e) = download::new_instance(
+					version,
+					id,
+					parallel.unwrap_or(config.parallel),
+					retries.unwrap_or(config.retries),
+				) {
+					eprintln!("ERROR: {}", e);
+					std::process::exit(1);
+				}
+			}
+			args::InstanceSub::Delete { id, yes } => {
+				if let Err(e) = instance::delete(&id, yes) {
+					eprintln!("ERROR: {}", e);
+					std::process::exit(1);
+				}
+			}
+			args::InstanceSub::Rename { id, new_id } => {
+				if let Err(e) = instance::rename(&id, &new_id) {
+					eprintln!("ERROR: {}", e);
+					std::process::exit(1);
+				}
+			}
+			args::InstanceSub::Clone { id, new_id } => {
+				if let Err(e) = instance::clone(&id, &new_id) {
+					eprintln!("ERROR: {}", e);
+					std::process::exit(1);
+				}
+			}
+			args::InstanceSub::Config {
+				id,
+				min_mem,
+				max_mem,
+				jvm_arg,
+				clear_jvm_args,
+				gc_preset,
+				env,
+				unset_env,
+			} => {
+				let changes = instance::ConfigChanges {
+					min_memory: min_mem,
+					max_memory: max_mem,
+					gc_preset,
+					jvm_args: jvm_arg,
+					clear_jvm_args,
+					env,
+					unset_env,
+				};
+				if let Err(e) = instance::config(&id, &changes) {
+					eprintln!("ERROR: {}", e);
+					std::process::exit(1);
+				}
+			}
+			args::InstanceSub::Verify {
+				id,
+				fix,
+				parallel,
+				retries,
+			} => {
+				if let Err(e) = download::verify_instance(
+					id,
+					fix,
+					parallel.unwrap_or(config.parallel),
+					retries.unwrap_or(config.retries),
+				) {
+					eprintln!("ERROR: {}", e);
+					std::process::exit(1);
+				}
+			}
 		},
 		args::SubCmd::Modloader(modldr_cmd) => match modldr_cmd.cmd {
-			args::LoaderSub::Install { loader } => println!("Install {}", loader),
+			args::LoaderSub::Install {
+				instance,
+				loader,
+				loader_version,
+				parallel,
+				retries,
+			} => {
+				if let Err(e) = loaders::install(
+					instance,
+					loader,
+					loader_version,
+					parallel.unwrap_or(config.parallel),
+					retries.unwrap_or(config.retries),
+				) {
+					eprintln!("ERROR: {}", e);
+					std::process::exit(1);
+				}
+			}
+			args::LoaderSub::Versions { instance, loader } => {
+				if let Err(e) = loaders::list_versions(instance, loader) {
+					eprintln!("ERROR: {}", e);
+					std::process::exit(1);
+				}
+			}
 		},
+		args::SubCmd::Java(java_cmd) => match java_cmd.cmd {
+			args::JavaSub::List { json, refresh } => {
+				if let Err(e) = java::list(json, refresh) {
+					eprintln!("ERROR: {}", e);
+					std::process::exit(1);
+				}
+			}
+			args::JavaSub::Use { instance, java } => {
+				if let Err(e) = java::pin(&instance, &java) {
+					eprintln!("ERROR: {}", e);
+					std::process::exit(1);
+				}
+			}
+		},
+		args::SubCmd::Config(config_cmd) => {
+			let result = match config_cmd.cmd {
+				args::ConfigSub::Get { key } => config::print(key.as_deref()),
+				args::ConfigSub::Set { key, value } => config::set(&key, &value),
+				args::ConfigSub::Unset { key } => config::unset(&key),
+				args::ConfigSub::Edit => config::edit(),
+			};
+			if let Err(e) = result {
+				eprintln!("ERROR: {}", e);
+				std::process::exit(1);
+			}
+		}
+		args::SubCmd::Gc { dry_run } => {
+			if let Err(e) = store::gc(dry_run) {
+				eprintln!("ERROR: {}", e);
+				std::process::exit(1);
+			}
+		}
 	}
 }
@@ -0,0 +1,328 @@
+use std::{
+	collections::HashMap,
+	io::{IsTerminal, Write},
+	sync::{
+		Mutex,
+		atomic::{AtomicBool, Ordering},
+	},
+	time::{Duration, Instant},
+};
+
+use crate::download::DownloadEntry;
+
+/// How often the bar is redrawn at most.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Set by `--quiet`.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Hide the progress of every download of this run.
+pub fn set_quiet(quiet: bool) {
+	QUIET.store(quiet, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressMode {
+	/// A single bar redrawn in place, for terminals.
+	Bar,
+	/// One line per finished file, for pipes and log files.
+	Lines,
+	/// No output at all, for `--quiet` and tests.
+	Hidden,
+}
+
+#[derive(Default)]
+struct State {
+	done_bytes: u64,
+	/// Part of `done_bytes` that was already on disk, left out of the throughput.
+	skipped_bytes: u64,
+	done_files: usize,
+	failed_files: usize,
+	/// Bytes received so far of files that are still downloading, by destination.
+	in_flight: HashMap<String, u64>,
+	last_draw: Option<Instant>,
+}
+
+/// Aggregate progress of one batch of downloads.
+pub struct Progress {
+	mode: ProgressMode,
+	total_bytes: u64,
+	total_files: usize,
+	start: Instant,
+	state: Mutex<State>,
+}
+
+impl Progress {
+	/// Progress for `queue`, drawn as a bar when stdout is a terminal and as plain
+	/// lines otherwise, unless `--quiet` hides it.
+	pub fn new(queue: &[DownloadEntry]) -> Self {
+		let mode = if QUIET.load(Ordering::Relaxed) {
+			ProgressMode::Hidden
+		} else if std::io::stdout().is_terminal() {
+			ProgressMode::Bar
+		} else {
+			ProgressMode::Lines
+		};
+		Self::with_mode(queue, mode)
+	}
+
+	pub fn with_mode(queue: &[DownloadEntry], mode: ProgressMode) -> Self {
+		Self {
+			mode,
+			total_bytes: queue.iter().map(|e| e.size.unwrap_or(0) as u64).sum(),
+			total_files: queue.len(),
+			start: Instant::now(),
+			state: Mutex::new(State::default()),
+		}
+	}
+
+	/// Record that `received` bytes of `entry` are on disk so far.
+	pub fn update(&self, entry: &DownloadEntry, received: u64) {
+		let mut state = self.state.lock().unwrap();
+		state.in_flight.insert(entry.destination.clone(), received);
+		self.draw(&mut state, false);
+	}
+
+	/// Record that `entry` was already on disk and didn't need downloading.
+	pub fn skip(&self, entry: &DownloadEntry) {
+		let mut state = self.state.lock().unwrap();
+		let size = entry.size.unwrap_or(0) as u64;
+		state.done_bytes += size;
+		state.skipped_bytes += size;
+		state.done_files += 1;
+		self.draw(&mut state, false);
+	}
+
+	/// Record that `entry` finished downloading, successfully or not.
+	pub fn finish_file(&self, entry: &DownloadEntry, ok: bool) {
+		let mut state = self.state.lock().unwrap();
+		let received = state.in_flight.remove(&entry.destination).unwrap_or(0);
+		if ok {
+			state.done_bytes += entry.size.map_or(received, |s| s as u64);
+			state.done_files += 1;
+			if self.mode == ProgressMode::Lines {
+				println!(
+					"[{}/{}] Downloaded {} ({} bytes)",
+					state.done_files,
+					self.total_files,
+					entry.name.clone().unwrap_or(entry.destination.clone()),
+					entry.size.unwrap_or(received as usize)
+				);
+			}
+		} else {
+			state.failed_files += 1;
+		}
+		self.draw(&mut state, false);
+	}
+
+	/// Draw the final state and end the bar's line.
+	pub fn finish(&self) {
+		let mut state = self.state.lock().unwrap();
+		self.draw(&mut state, true);
+		match self.mode {
+			ProgressMode::Bar => println!(),
+			ProgressMode::Lines => println!("{}", self.summary(&state)),
+			ProgressMode::Hidden => (),
+		}
+	}
+
+	fn draw(&self, state: &mut State, force: bool) {
+		if self.mode != ProgressMode::Bar {
+			return;
+		}
+		let now = Instant::now();
+		if !force
+			&& state
+				.last_draw
+				.is_some_and(|t| now.duration_since(t) < REDRAW_INTERVAL)
+		{
+			return;
+		}
+		state.last_draw = Some(now);
+
+		let fraction = if self.total_bytes == 0 {
+			1.0
+		} else {
+			(self.bytes(state) as f64 / self.total_bytes as f64).min(1.0)
+		};
+		let bar_len = 15;
+		let filled_len = (bar_len as f64 * fraction) as usize;
+		let bar = "=".repeat(filled_len) + &"-".repeat(bar_len - filled_len);
+
+		let line = format!(
+			"[{}] {:.1}% - {}",
+			bar,
+			fraction * 100.0,
+			self.summary(state)
+		);
+		// A wrapped line can't be cleared with `\r`, so every redraw would leave one behind.
+		print!("\r\x1b[2K{}", fit(&line, terminal_width()));
+		_ = std::io::stdout().flush();
+	}
+
+	fn bytes(&self, state: &State) -> u64 {
+		state.done_bytes + state.in_flight.values().sum::<u64>()
+	}
+
+	fn summary(&self, state: &State) -> String {
+		let bytes = self.bytes(state);
+		let elapsed = self.start.elapsed().as_secs_f64();
+		let rate = if elapsed > 0.0 {
+			(bytes - state.skipped_bytes) as f64 / elapsed
+		} else {
+			0.0
+		};
+		let eta = if rate > 0.0 {
+			format_duration(self.total_bytes.saturating_sub(bytes) as f64 / rate)
+		} else {
+			"--".to_string()
+		};
+		let mut line = format!(
+			"{}/{} files, {}/{}, {}/s, ETA {}",
+			state.done_files,
+			self.total_files,
+			format_bytes(bytes),
+			format_bytes(self.total_bytes),
+			format_bytes(rate as u64),
+			eta
+		);
+		if state.failed_files > 0 {
+			line.push_str(&format!(", {} failed", state.failed_files));
+		}
+		line
+	}
+}
+
+/// Cut `line` to fit a terminal `width` columns wide, leaving the last column
+/// free so the cursor doesn't wrap.
+fn fit(line: &str, width: usize) -> String {
+	if line.chars().count() < width {
+		return line.to_string();
+	}
+	let kept: String = line.chars().take(width.saturating_sub(4)).collect();
+	format!("{}...", kept)
+}
+
+/// Columns of the terminal, found like Python's `shutil.get_terminal_size`:
+/// `COLUMNS`, then the terminal itself, then 80.
+fn terminal_width() -> usize {
+	if let Some(columns) = std::env::var("COLUMNS")
+		.ok()
+		.and_then(|c| c.parse().ok())
+		.filter(|c| *c > 0)
+	{
+		return columns;
+	}
+	#[cfg(unix)]
+	{
+		let mut size = libc::winsize {
+			ws_row: 0,
+			ws_col: 0,
+			ws_xpixel: 0,
+			ws_ypixel: 0,
+		};
+		// SAFETY: TIOCGWINSZ only writes a `winsize` into the struct it is given.
+		let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+		if ok && size.ws_col > 0 {
+			return size.ws_col as usize;
+		}
+	}
+	80
+}
+
+fn format_bytes(bytes: u64) -> String {
+	const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+	let mut value = bytes as f64;
+	let mut unit = 0;
+	while value >= 1024.0 && unit < UNITS.len() - 1 {
+		value /= 1024.0;
+		unit += 1;
+	}
+	if unit == 0 {
+		format!("{} {}", bytes, UNITS[0])
+	} else {
+		format!("{:.1} {}", value, UNITS[unit])
+	}
+}
+
+fn format_duration(secs: f64) -> String {
+	let secs = secs.round() as u64;
+	if secs >= 60 {
+		format!("{}m{:02}s", secs / 60, secs % 60)
+	} else {
+		format!("{}s", secs)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn entry(destination: &str, size: Option<usize>) -> DownloadEntry {
+		DownloadEntry {
+			url: format!("https://example.com/{}", destination),
+			destination: destination.to_string(),
+			size,
+			sha1: None,
+			name: None,
+			shared: false,
+		}
+	}
+
+	#[test]
+	fn counts_files_and_bytes() {
+		let queue = [
+			entry("a", Some(100)),
+			entry("b", Some(200)),
+			entry("c", Some(300)),
+			entry("d", None),
+		];
+		let progress = Progress::with_mode(&queue, ProgressMode::Hidden);
+		assert_eq!((progress.total_files, progress.total_bytes), (4, 600));
+
+		progress.skip(&queue[0]);
+		progress.update(&queue[1], 50);
+		progress.update(&queue[2], 120);
+		progress.update(&queue[3], 40);
+		{
+			let state = progress.state.lock().unwrap();
+			assert_eq!(progress.bytes(&state), 310);
+			assert_eq!(state.skipped_bytes, 100);
+			assert_eq!(state.in_flight.len(), 3);
+		}
+
+		// A finished file counts with its full size, a failed one not at all,
+		// and one of unknown size with what was received.
+		progress.finish_file(&queue[1], true);
+		progress.finish_file(&queue[2], false);
+		progress.finish_file(&queue[3], true);
+		let state = progress.state.lock().unwrap();
+		assert!(state.in_flight.is_empty());
+		assert_eq!(state.done_bytes, 340);
+		assert_eq!((state.done_files, state.failed_files), (3, 1));
+		let summary = progress.summary(&state);
+		assert!(summary.starts_with("3/4 files, 340 B/600 B"), "{}", summary);
+		assert!(summary.ends_with(", 1 failed"), "{}", summary);
+	}
+
+	#[test]
+	fn fits_the_terminal() {
+		let line =
+			"[===============] 45.3% - 1234/4012 files, 123.4 MiB/456.7 MiB, 12.3 MiB/s, ETA 1m05s";
+		assert_eq!(fit(line, 120), line);
+		let cut = fit(line, 80);
+		assert_eq!(cut.chars().count(), 79);
+		assert!(cut.ends_with("...") && line.starts_with(&cut[..76]));
+		assert_eq!(fit("abc", 4), "abc");
+		assert_eq!(fit("abcd", 4), "...");
+	}
+
+	#[test]
+	fn formats_sizes_and_durations() {
+		assert_eq!(format_bytes(512), "512 B");
+		assert_eq!(format_bytes(1536), "1.5 KiB");
+		assert_eq!(format_bytes(3 << 30), "3.0 GiB");
+		assert_eq!(format_duration(42.4), "42s");
+		assert_eq!(format_duration(125.0), "2m05s");
+	}
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Launcher features a rule can ask about (`is_demo_user`, `has_custom_resolution`, ...).
+pub type Features = HashMap<String, bool>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+	Allow,
+	Disallow,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct OsRule {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub name: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub arch: Option<String>,
+	/// A regex matched against the OS version, e.g. `^10\\.` for Windows 10.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub version: Option<VersionPattern>,
+}
+
+/// The regex of an [`OsRule`], compiled once when the rule is read rather than
+/// on every evaluation. Written back as the pattern it was read from.
+#[derive(Debug, Clone)]
+pub struct VersionPattern(Regex);
+
+impl Serialize for VersionPattern {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(self.0.as_str())
+	}
+}
+
+impl<'de> Deserialize<'de> for VersionPattern {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let pattern = String::deserialize(deserializer)?;
+		Regex::new(&pattern)
+			.map(VersionPattern)
+			.map_err(serde::de::Error::custom)
+	}
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Rule {
+	pub action: RuleAction,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub os: Option<OsRule>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub features: Option<Features>,
+}
+
+/// The machine rules are evaluated against. Use [`Environment::host`] outside of tests.
+#[derive(Debug, Clone)]
+pub struct Environment {
+	/// OS name as Mojang spells it: `windows`, `osx` or `linux`.
+	pub os_name: String,
+	/// Normalised CPU architecture: `x86`, `x86_64`, `arm32` or `arm64`.
+	pub os_arch: String,
+	pub os_version: Option<String>,
+}
+
+impl Environment {
+	pub fn host() -> Self {
+		Self {
+			os_name: match std::env::consts::OS {
+				"macos" => "osx".to_string(),
+				other => other.to_string(),
+			},
+			os_arch: normalize_arch(std::env::consts::ARCH).to_string(),
+			os_version: host_os_version(),
+		}
+	}
+
+	/// The value of `${arch}` in legacy `natives` classifiers (`natives-windows-${arch}`).
+	pub fn arch_bits(&self) -> &'static str {
+		match self.os_arch.as_str() {
+			"x86" | "arm32" => "32",
+			_ => "64",
+		}
+	}
+}
+
+/// Map the many spellings of an architecture (Rust, Java and Mojang ones) onto one name.
+pub fn normalize_arch(arch: &str) -> &str {
+	match arch {
+		"x86_64" | "amd64" | "x64" => "x86_64",
+		"x86" | "i386" | "i686" => "x86",
+		"aarch64" | "arm64" => "arm64",
+		"arm" | "arm32" => "arm32",
+		other => other,
+	}
+}
+
+#[cfg(target_os = "linux")]
+fn host_os_version() -> Option<String> {
+	std::fs::read_to_string("/proc/sys/kernel/osrelease")
+		.ok()
+		.map(|v| v.trim().to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn host_os_version() -> Option<String> {
+	None
+}
+
+impl Rule {
+	/// Whether every condition of this rule holds in `env`.
+	pub fn matches(&self, env: &Environment, features: &Features) -> bool {
+		if let Some(os) = &self.os {
+			if os.name.as_deref().is_some_and(|n| n != env.os_name) {
+				return false;
+			}
+			if os
+				.arch
+				.as_deref()
+				.is_some_and(|a| normalize_arch(a) != env.os_arch)
+			{
+				return false;
+			}
+			if let Some(VersionPattern(re)) = &os.version
+				&& !env.os_version.as_deref().is_some_and(|v| re.is_match(v))
+			{
+				return false;
+			}
+		}
+		if let Some(wanted) = &self.features {
+			for (k, v) in wanted {
+				if features.get(k).copied().unwrap_or(false) != *v {
+					return false;
+				}
+			}
+		}
+		true
+	}
+}
+
+/// Evaluate a rule list the way the vanilla launcher does: no rules means
+/// allowed, otherwise the last matching rule decides and the default is disallow.
+pub fn rules_allow(rules: &[Rule], env: &Environment, features: &Features) -> bool {
+	if rules.is_empty() {
+		return true;
+	}
+	let mut allowed = false;
+	for r in rules {
+		if r.matches(env, features) {
+			allowed = r.action == RuleAction::Allow;
+		}
+	}
+	allowed
+}
@@ -0,0 +1,395 @@
+use std::{
+	collections::{BTreeMap, HashMap},
+	fs::{self, File},
+	io::BufReader,
+	path::{Path, PathBuf},
+};
+
+use reqwest::Client;
+use serde::Deserialize;
+use tokio::runtime::Runtime;
+
+use crate::config;
+use crate::download::{self, DownloadEntry};
+use crate::helpers::get_data_folder;
+use crate::rules::Environment;
+use crate::version::{Artifact, VersionJson};
+
+/// Every Java runtime Mojang publishes, by platform and component.
+const RUNTIMES_URL: &str = "https://piston-meta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
+
+/// The runtime of versions whose JSON predates `javaVersion`, all of which run on Java 8.
+const LEGACY_COMPONENT: &str = "jre-legacy";
+
+/// Written into a runtime's folder once every file of it is in place.
+const COMPLETE_FILE: &str = ".version";
+
+#[derive(Debug, Deserialize)]
+struct Release {
+	manifest: Artifact,
+	version: ReleaseVersion,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseVersion {
+	name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RuntimeManifest {
+	files: BTreeMap<String, RuntimeFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum RuntimeFile {
+	File {
+		#[serde(default)]
+		executable: bool,
+		downloads: FileDownloads,
+	},
+	Directory,
+	Link {
+		target: String,
+	},
+}
+
+#[derive(Debug, Deserialize)]
+struct FileDownloads {
+	raw: Artifact,
+	lzma: Option<Artifact>,
+}
+
+/// The runtime component a version asks for, e.g. `java-runtime-gamma`.
+pub fn component(version: &VersionJson) -> &str {
+	version
+		.java_version
+		.as_ref()
+		.map_or(LEGACY_COMPONENT, |j| j.component.as_str())
+}
+
+/// Mojang's name for a platform, or `None` if it publishes no runtimes for it.
+fn platform(env: &Environment) -> Option<&'static str> {
+	match (env.os_name.as_str(), env.os_arch.as_str()) {
+		("linux", "x86_64") => Some("linux"),
+		("linux", "x86") => Some("linux-i386"),
+		("osx", "x86_64") => Some("mac-os"),
+		("osx", "arm64") => Some("mac-os-arm64"),
+		("windows", "x86_64") => Some("windows-x64"),
+		("windows", "x86") => Some("windows-x86"),
+		("windows", "arm64") => Some("windows-arm64"),
+		_ => None,
+	}
+}
+
+/// Where a runtime component is stored, shared by every instance.
+fn runtime_folder(component: &str) -> PathBuf {
+	get_data_folder(Some("runtimes")).join(component)
+}
+
+/// The Java home inside a runtime folder.
+fn java_home(dir: &Path, env: &Environment) -> PathBuf {
+	match env.os_name.as_str() {
+		"osx" => dir.join("jre.bundle/Contents/Home"),
+		_ => dir.to_path_buf(),
+	}
+}
+
+/// The `java` executable inside a runtime folder.
+fn java_path(dir: &Path, env: &Environment) -> PathBuf {
+	crate::java::executable(&java_home(dir, env))
+}
+
+/// Java homes of every completely installed runtime.
+pub fn homes() -> Vec<PathBuf> {
+	let env = Environment::host();
+	let Ok(entries) = fs::read_dir(get_data_folder(Some("runtimes"))) else {
+		return vec![];
+	};
+	let mut homes: Vec<PathBuf> = entries
+		.filter_map(|e| e.ok())
+		.map(|e| e.path())
+		.filter(|dir| dir.join(COMPLETE_FILE).is_file())
+		.map(|dir| java_home(&dir, &env))
+		.collect();
+	homes.sort();
+	homes
+}
+
+/// The `java` executable of an already installed runtime component.
+fn installed(component: &str) -> Option<PathBuf> {
+	let dir = runtime_folder(component);
+	let java = java_path(&dir, &Environment::host());
+	(dir.join(COMPLETE_FILE).is_file() && java.is_file()).then_some(java)
+}
+
+/// Make sure the runtime `version` asks for is installed and return its `java`
+/// executable. Returns `None` on platforms Mojang has no runtimes for.
+pub fn provision(
+	rt: &Runtime,
+	version: &VersionJson,
+	parallel: usize,
+	retries: usize,
+) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+	let component = component(version);
+	if let Some(java) = installed(component) {
+		return Ok(Some(java));
+	}
+	let env = Environment::host();
+	let Some(platform) = platform(&env) else {
+		return Ok(None);
+	};
+	install(rt, component, platform, &env, parallel, retries).map(Some)
+}
+
+/// [`provision`] outside of an install, with the configured download settings.
+pub fn provision_now(version: &VersionJson) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+	if let Some(java) = installed(component(version)) {
+		return Ok(Some(java));
+	}
+	let config = config::get();
+	provision(&Runtime::new()?, version, config.parallel, config.retries)
+}
+
+async fn get_json<T: serde::de::DeserializeOwned>(
+	client: &Client,
+	url: &str,
+) -> Result<T, Box<dyn std::error::Error>> {
+	let raw = client
+		.get(config::get().mirrors.apply(url))
+		.send()
+		.await?
+		.error_for_status()?
+		.text()
+		.await?;
+	serde_json::from_str(&raw)
+		.map_err(|e| format!("Invalid runtime manifest {}: {}", url, e).into())
+}
+
+fn install(
+	rt: &Runtime,
+	component: &str,
+	platform: &str,
+	env: &Environment,
+	parallel: usize,
+	retries: usize,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+	let client = Client::new();
+	let (release, manifest) = rt.block_on(async {
+		let mut runtimes: HashMap<String, HashMap<String, Vec<Release>>> =
+			get_json(&client, RUNTIMES_URL).await?;
+		let release = runtimes
+			.get_mut(platform)
+			.and_then(|p| p.remove(component))
+			.and_then(|r| r.into_iter().next())
+			.ok_or(format!(
+				"Mojang has no {} runtime for {}",
+				component, platform
+			))?;
+		let manifest: RuntimeManifest = get_json(&client, &release.manifest.url).await?;
+		Ok::<_, Box<dyn std::error::Error>>((release, manifest))
+	})?;
+
+	println!(
+		"Installing Java runtime {} ({})...",
+		component, release.version.name
+	);
+	let dir = runtime_folder(component);
+	fs::create_dir_all(&dir)?;
+	_ = fs::remove_file(dir.join(COMPLETE_FILE));
+
+	let mut queue = vec![];
+	let mut packed = vec![];
+	for (path, file) in &manifest.files {
+		match file {
+			RuntimeFile::Directory => fs::create_dir_all(dir.join(path))?,
+			RuntimeFile::File { downloads, .. } => {
+				let raw = file_entry(component, path, &downloads.raw);
+				if download::entry_is_valid(&raw, &dir) {
+					continue;
+				}
+				// The LZMA variant is a fraction of the size; unpacked once it is down.
+				match &downloads.lzma {
+					Some(lzma) => {
+						let entry = file_entry(component, &format!("{}.lzma", path), lzma);
+						packed.push((download::entry_path(&entry, &dir), raw));
+						queue.push(entry);
+					}
+					None => queue.push(raw),
+				}
+			}
+			RuntimeFile::Link { .. } => (),
+		}
+	}
+	download::download_queue(rt, queue, &dir, parallel, retries)?;
+
+	for (lzma, raw) in &packed {
+		unpack(lzma, &download::entry_path(raw, &dir), raw.sha1.as_deref())?;
+	}
+	for (path, file) in &manifest.files {
+		match file {
+			RuntimeFile::File {
+				executable: true, ..
+			} => set_executable(&dir.join(path))?,
+			RuntimeFile::Link { target } => link(target, &dir.join(path))?,
+			_ => (),
+		}
+	}
+
+	fs::write(dir.join(COMPLETE_FILE), &release.version.name)?;
+	println!("Installed Java runtime {}.", component);
+	Ok(java_path(&dir, env))
+}
+
+fn file_entry(component: &str, path: &str, artifact: &Artifact) -> DownloadEntry {
+	DownloadEntry {
+		url: artifact.url.clone(),
+		destination: format!("runtimes/{}/{}", component, path),
+		size: artifact.size.map(|s| s as usize),
+		sha1: artifact.sha1.clone(),
+		name: Some(path.to_string()),
+		shared: true,
+	}
+}
+
+/// Decompress a downloaded `.lzma` file next to it, check the result and remove the archive.
+fn unpack(lzma: &Path, dest: &Path, sha1: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+	let mut input = BufReader::new(File::open(lzma)?);
+	let mut output = File::create(dest)?;
+	lzma_rs::lzma_decompress(&mut input, &mut output)
+		.map_err(|e| format!("Failed to unpack {}: {}", lzma.display(), e))?;
+	drop(output);
+	fs::remove_file(lzma)?;
+
+	if let Some(expected) = sha1 {
+		let actual = download::sha1_file(dest)?;
+		if !actual.eq_ignore_ascii_case(expected) {
+			_ = fs::remove_file(dest);
+			return Err(format!(
+				"{} has SHA1 {} after unpacking, expected {}",
+				dest.display(),
+				actual,
+				expected
+			)
+			.into());
+		}
+	}
+	Ok(())
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> std::io::Result<()> {
+	use std::os::unix::fs::PermissionsExt;
+	fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> std::io::Result<()> {
+	Ok(())
+}
+
+#[cfg(unix)]
+fn link(target: &str, path: &Path) -> std::io::Result<()> {
+	if fs::symlink_metadata(path).is_ok() {
+		fs::remove_file(path)?;
+	}
+	if let Some(parent) = path.parent() {
+		fs::create_dir_all(parent)?;
+	}
+	std::os::unix::fs::symlink(target, path)
+}
+
+/// Only the Unix runtimes contain links.
+#[cfg(not(unix))]
+fn link(_target: &str, _path: &Path) -> std::io::Result<()> {
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sha1::{Digest, Sha1};
+
+	fn env(os_name: &str, os_arch: &str) -> Environment {
+		Environment {
+			os_name: os_name.to_string(),
+			os_arch: os_arch.to_string(),
+			os_version: None,
+		}
+	}
+
+	#[test]
+	fn components_and_platforms() {
+		let modern = VersionJson::parse(include_str!("../tests/fixtures/1.20.1.json")).unwrap();
+		let legacy = VersionJson::parse(include_str!("../tests/fixtures/1.12.2.json")).unwrap();
+		assert_eq!(component(&modern), "java-runtime-gamma");
+		assert_eq!(component(&legacy), LEGACY_COMPONENT);
+
+		assert_eq!(platform(&env("linux", "x86_64")), Some("linux"));
+		assert_eq!(platform(&env("osx", "arm64")), Some("mac-os-arm64"));
+		assert_eq!(platform(&env("windows", "x86")), Some("windows-x86"));
+		assert_eq!(platform(&env("linux", "arm64")), None);
+
+		let dir = Path::new("runtimes/jre-legacy");
+		assert_eq!(
+			java_path(dir, &env("osx", "x86_64")),
+			dir.join("jre.bundle/Contents/Home/bin/java")
+		);
+	}
+
+	#[test]
+	fn parses_runtime_manifest() {
+		let manifest: RuntimeManifest = serde_json::from_str(
+			r#"{"files": {
+				"bin": {"type": "directory"},
+				"bin/java": {"type": "file", "executable": true, "downloads": {
+					"lzma": {"sha1": "aa", "size": 10, "url": "https://example.com/java.lzma"},
+					"raw": {"sha1": "bb", "size": 20, "url": "https://example.com/java"}
+				}},
+				"legal/java.base/LICENSE": {"type": "link", "target": "../java.desktop/LICENSE"}
+			}}"#,
+		)
+		.unwrap();
+		assert!(matches!(manifest.files["bin"], RuntimeFile::Directory));
+		match &manifest.files["bin/java"] {
+			RuntimeFile::File {
+				executable,
+				downloads,
+			} => {
+				assert!(executable);
+				assert_eq!(downloads.raw.size, Some(20));
+				assert_eq!(
+					downloads.lzma.as_ref().unwrap().url,
+					"https://example.com/java.lzma"
+				);
+			}
+			other => panic!("expected a file, got {:?}", other),
+		}
+		assert!(matches!(
+			&manifest.files["legal/java.base/LICENSE"],
+			RuntimeFile::Link { target } if target == "../java.desktop/LICENSE"
+		));
+	}
+
+	#[test]
+	fn unpacks_lzma_files() {
+		let dir = std::env::temp_dir().join(format!("vanta-runtime-{}", std::process::id()));
+		fs::create_dir_all(&dir).unwrap();
+		let body = b"#!/bin/sh\necho java\n".repeat(50);
+		let mut packed = vec![];
+		lzma_rs::lzma_compress(&mut &body[..], &mut packed).unwrap();
+		let (lzma, dest) = (dir.join("java.lzma"), dir.join("java"));
+
+		fs::write(&lzma, &packed).unwrap();
+		let sha1 = format!("{:x}", Sha1::digest(&body));
+		unpack(&lzma, &dest, Some(&sha1)).unwrap();
+		assert_eq!(fs::read(&dest).unwrap(), body);
+		assert!(!lzma.exists());
+
+		fs::write(&lzma, &packed).unwrap();
+		assert!(unpack(&lzma, &dest, Some("0000")).is_err());
+		assert!(!dest.exists());
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -0,0 +1,407 @@
+use std::{
+	collections::{HashMap, HashSet},
+	fs,
+	path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::rules::Rule;
+
+/// The vanilla version JSON of an instance, as saved by `install_minecraft`.
+pub const CLIENT_JSON: &str = "versions/client.json";
+/// A mod loader profile that `inheritsFrom` the vanilla version, if one is installed.
+pub const LOADER_JSON: &str = "versions/loader.json";
+
+/// Longest `inheritsFrom` chain followed before giving up.
+const MAX_INHERITANCE_DEPTH: usize = 8;
+
+/// A version JSON (`<version>.json`) as served by Mojang's piston-meta.
+///
+/// Only what the launcher needs is modelled; unknown top-level keys are kept
+/// in `extra` so the file can be written back unchanged.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionJson {
+	pub id: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub inherits_from: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub r#type: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub main_class: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub arguments: Option<Arguments>,
+	/// Pre-1.13 versions have a single space-separated game argument string instead of `arguments`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub minecraft_arguments: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub asset_index: Option<AssetIndex>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub assets: Option<String>,
+	/// `client`, `server`, `client_mappings`, ...
+	#[serde(default, skip_serializing_if = "HashMap::is_empty")]
+	pub downloads: HashMap<String, Artifact>,
+	#[serde(default)]
+	pub libraries: Vec<Library>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub java_version: Option<JavaVersion>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub logging: Option<Logging>,
+	#[serde(flatten)]
+	pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl VersionJson {
+	/// Parse a version JSON, naming the offending field if it is malformed.
+	pub fn parse(raw: &str) -> Result<Self, Box<dyn std::error::Error>> {
+		serde_json::from_str::<Self>(raw).map_err(|e| format!("Invalid version JSON: {}", e).into())
+	}
+
+	pub fn read(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+		let raw = fs::read_to_string(path)
+			.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+		Self::parse(&raw).map_err(|e| format!("{}: {}", path.display(), e).into())
+	}
+
+	/// Merge this version onto the `parent` it inherits from, the way the
+	/// vanilla launcher does: values set here win, `libraries` come before the
+	/// parent's (replacing parent libraries with the same group, artifact and
+	/// classifier) and `arguments` are appended to the parent's.
+	pub fn inherit(self, parent: VersionJson) -> VersionJson {
+		let own: HashSet<String> = self.libraries.iter().map(library_key).collect();
+		let mut libraries = self.libraries;
+		libraries.extend(
+			parent
+				.libraries
+				.into_iter()
+				.filter(|l| !own.contains(&library_key(l))),
+		);
+
+		let arguments = match (parent.arguments, self.arguments) {
+			(Some(mut p), Some(c)) => {
+				p.game.extend(c.game);
+				p.jvm.extend(c.jvm);
+				Some(p)
+			}
+			(p, c) => c.or(p),
+		};
+
+		let mut downloads = parent.downloads;
+		downloads.extend(self.downloads);
+		let mut extra = parent.extra;
+		extra.extend(self.extra);
+
+		VersionJson {
+			id: self.id,
+			inherits_from: parent.inherits_from,
+			r#type: self.r#type.or(parent.r#type),
+			main_class: self.main_class.or(parent.main_class),
+			arguments,
+			minecraft_arguments: self.minecraft_arguments.or(parent.minecraft_arguments),
+			asset_index: self.asset_index.or(parent.asset_index),
+			assets: self.assets.or(parent.assets),
+			downloads,
+			libraries,
+			java_version: self.java_version.or(parent.java_version),
+			logging: self.logging.or(parent.logging),
+			extra,
+		}
+	}
+
+	/// Follow `inheritsFrom` up the chain, loading each parent with `load`, and
+	/// merge everything into one effective version.
+	pub fn resolve(
+		self,
+		mut load: impl FnMut(&str) -> Result<VersionJson, Box<dyn std::error::Error>>,
+	) -> Result<VersionJson, Box<dyn std::error::Error>> {
+		let mut seen = vec![self.id.clone()];
+		let mut version = self;
+
+		while let Some(parent_id) = version.inherits_from.clone() {
+			if seen.contains(&parent_id) {
+				return Err(format!(
+					"Version {} inherits from itself ({} -> {})",
+					parent_id,
+					seen.join(" -> "),
+					parent_id
+				)
+				.into());
+			}
+			if seen.len() > MAX_INHERITANCE_DEPTH {
+				return Err(format!("Inheritance chain of {} is too long", seen[0]).into());
+			}
+			let parent = load(&parent_id)
+				.map_err(|e| format!("Failed to load parent version {}: {}", parent_id, e))?;
+			seen.push(parent_id);
+			version = version.inherit(parent);
+		}
+
+		Ok(version)
+	}
+}
+
+/// `group:artifact[:classifier]` of a library, i.e. its Maven coordinate without the version.
+fn library_key(lib: &Library) -> String {
+	let parts: Vec<&str> = lib.name.split(':').collect();
+	match parts.as_slice() {
+		[group, artifact, _, classifier, ..] => format!("{}:{}:{}", group, artifact, classifier),
+		[group, artifact, ..] => format!("{}:{}", group, artifact),
+		_ => lib.name.clone(),
+	}
+}
+
+/// The effective version JSON of an instance: its loader profile merged onto
+/// the vanilla version if a loader is installed, else just the vanilla one.
+/// Parents other than the vanilla version are read from `versions/<id>.json`.
+pub fn read_instance_version(inst_dir: &Path) -> Result<VersionJson, Box<dyn std::error::Error>> {
+	let client = VersionJson::read(&inst_dir.join(CLIENT_JSON))?;
+	let loader = inst_dir.join(LOADER_JSON);
+	if !loader.exists() {
+		return Ok(client);
+	}
+
+	VersionJson::read(&loader)?.resolve(|id| {
+		if id == client.id {
+			Ok(client.clone())
+		} else {
+			VersionJson::read(&inst_dir.join("versions").join(format!("{}.json", id)))
+		}
+	})
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Arguments {
+	#[serde(default)]
+	pub game: Vec<Argument>,
+	#[serde(default)]
+	pub jvm: Vec<Argument>,
+}
+
+/// A single entry of `arguments.game` / `arguments.jvm`: either a plain string
+/// or a `{rules, value}` object that only applies when its rules allow it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Argument {
+	Plain(String),
+	Conditional {
+		rules: Vec<Rule>,
+		value: ArgumentValue,
+	},
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ArgumentValue {
+	Single(String),
+	Many(Vec<String>),
+}
+
+/// A downloadable file. Library artifacts carry a `path` below the libraries
+/// folder; the client jar and friends don't.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Artifact {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub path: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub sha1: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub size: Option<u64>,
+	#[serde(default)]
+	pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetIndex {
+	pub id: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub sha1: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub size: Option<u64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub total_size: Option<u64>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JavaVersion {
+	/// Name of the Mojang Java runtime, e.g. `java-runtime-gamma`.
+	pub component: String,
+	pub major_version: u32,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Logging {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub client: Option<LoggingConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LoggingConfig {
+	/// JVM argument with a `${path}` placeholder for the config file.
+	pub argument: String,
+	pub file: LoggingFile,
+	pub r#type: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LoggingFile {
+	pub id: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub sha1: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub size: Option<u64>,
+	pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Library {
+	/// Maven coordinate, `group:artifact:version[:classifier]`.
+	pub name: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub downloads: Option<LibraryDownloads>,
+	/// Maven repository to fetch the library from when it has no `downloads` (mod loaders).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub url: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub sha1: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub size: Option<u64>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub rules: Vec<Rule>,
+	/// Classifier of the natives jar per OS name, possibly containing `${arch}`.
+	#[serde(default, skip_serializing_if = "HashMap::is_empty")]
+	pub natives: HashMap<String, String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub extract: Option<Extract>,
+	#[serde(flatten)]
+	pub extra: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LibraryDownloads {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub artifact: Option<Artifact>,
+	#[serde(default, skip_serializing_if = "HashMap::is_empty")]
+	pub classifiers: HashMap<String, Artifact>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Extract {
+	#[serde(default)]
+	pub exclude: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn raw_fixture(name: &str) -> String {
+		let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+		std::fs::read_to_string(path).unwrap()
+	}
+
+	#[test]
+	fn fixtures_round_trip() {
+		for name in [
+			"1.12.2.json",
+			"1.20.1.json",
+			"24w14a.json",
+			"fabric-loader-0.15.11-1.20.1.json",
+		] {
+			let raw = raw_fixture(name);
+			let version = VersionJson::parse(&raw).unwrap();
+			let original: serde_json::Value = serde_json::from_str(&raw).unwrap();
+			assert_eq!(
+				serde_json::to_value(&version).unwrap(),
+				original,
+				"{}",
+				name
+			);
+		}
+	}
+
+	#[test]
+	fn fixtures_are_typed() {
+		let old = VersionJson::parse(&raw_fixture("1.12.2.json")).unwrap();
+		assert!(old.arguments.is_none());
+		assert!(old.minecraft_arguments.is_some());
+		assert!(old.libraries.iter().any(|l| !l.natives.is_empty()));
+
+		let modern = VersionJson::parse(&raw_fixture("1.20.1.json")).unwrap();
+		assert_eq!(
+			modern.main_class.as_deref(),
+			Some("net.minecraft.client.main.Main")
+		);
+		assert_eq!(modern.java_version.unwrap().major_version, 17);
+		assert!(modern.logging.unwrap().client.is_some());
+
+		let snapshot = VersionJson::parse(&raw_fixture("24w14a.json")).unwrap();
+		assert_eq!(snapshot.r#type.as_deref(), Some("snapshot"));
+		assert_eq!(snapshot.java_version.unwrap().major_version, 21);
+	}
+
+	#[test]
+	fn loader_inherits_from_vanilla() {
+		let vanilla = VersionJson::parse(&raw_fixture("1.20.1.json")).unwrap();
+		let fabric = VersionJson::parse(&raw_fixture("fabric-loader-0.15.11-1.20.1.json")).unwrap();
+		let merged = fabric
+			.resolve(|id| {
+				assert_eq!(id, "1.20.1");
+				Ok(vanilla.clone())
+			})
+			.unwrap();
+
+		assert_eq!(merged.id, "fabric-loader-0.15.11-1.20.1");
+		assert!(merged.inherits_from.is_none());
+		assert_eq!(
+			merged.main_class.as_deref(),
+			Some("net.fabricmc.loader.impl.launch.knot.KnotClient")
+		);
+		assert_eq!(merged.java_version.unwrap().major_version, 17);
+		assert!(merged.downloads.contains_key("client"));
+		assert_eq!(merged.libraries[0].name, "org.ow2.asm:asm:9.6");
+		assert_eq!(merged.libraries.len(), 3 + vanilla.libraries.len());
+
+		let arguments = merged.arguments.unwrap();
+		let vanilla_arguments = vanilla.arguments.unwrap();
+		assert_eq!(arguments.jvm.len(), vanilla_arguments.jvm.len() + 1);
+		assert_eq!(arguments.game.len(), vanilla_arguments.game.len());
+	}
+
+	#[test]
+	fn child_libraries_replace_parent_ones() {
+		let parent = VersionJson::parse(
+			r#"{"id": "p", "libraries": [{"name": "org.ow2.asm:asm:9.1"}, {"name": "a:b:1"}]}"#,
+		)
+		.unwrap();
+		let child = VersionJson::parse(
+			r#"{"id": "c", "inheritsFrom": "p", "libraries": [{"name": "org.ow2.asm:asm:9.6"}]}"#,
+		)
+		.unwrap();
+		let merged = child.resolve(|_| Ok(parent.clone())).unwrap();
+		let names: Vec<&str> = merged.libraries.iter().map(|l| l.name.as_str()).collect();
+		assert_eq!(names, ["org.ow2.asm:asm:9.6", "a:b:1"]);
+	}
+
+	#[test]
+	fn inheritance_cycles_are_errors() {
+		let a = VersionJson::parse(r#"{"id": "a", "inheritsFrom": "b"}"#).unwrap();
+		let b = VersionJson::parse(r#"{"id": "b", "inheritsFrom": "a"}"#).unwrap();
+		let err = a.resolve(|_| Ok(b.clone())).unwrap_err();
+		assert!(err.to_string().contains("inherits from itself"), "{}", err);
+	}
+
+	#[test]
+	fn malformed_versions_are_errors() {
+		let err = VersionJson::parse(r#"{"id": "x", "libraries": [{"rules": []}]}"#).unwrap_err();
+		assert!(err.to_string().contains("missing field `name`"), "{}", err);
+
+		let err =
+			VersionJson::parse(r#"{"id": "x", "javaVersion": {"component": 17}}"#).unwrap_err();
+		assert!(err.to_string().contains("Invalid version JSON"), "{}", err);
+	}
+}